@@ -0,0 +1,128 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{to_local_date, Task};
+
+pub fn to_csv(tasks: &[Task]) -> String {
+    let mut csv = String::from("id,name,created_at,completed_at\n");
+    for task in tasks {
+        let row = [
+            task.id.to_string(),
+            escape_csv(&task.name),
+            rfc3339(&task.created_at),
+            task.completed_at
+                .map(|completed_at| rfc3339(&completed_at))
+                .unwrap_or_default(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn to_markdown(tasks: &[Task]) -> String {
+    let mut markdown = String::new();
+    for task in tasks {
+        let checkbox = match task.completed_at {
+            Some(_) => "[x]",
+            None => "[ ]",
+        };
+        markdown.push_str(&format!(
+            "- {} {} ({})\n",
+            checkbox,
+            task.name,
+            to_local_date(&task.created_at)
+        ));
+    }
+    markdown
+}
+
+/// One line per task for `todo list`, prefixed with its id.
+pub fn to_text(tasks: &[Task]) -> String {
+    let mut text = String::new();
+    for task in tasks {
+        let checkbox = match task.completed_at {
+            Some(_) => "[x]",
+            None => "[ ]",
+        };
+        text.push_str(&format!("{:>4} {} {}", task.id, checkbox, task.name));
+        if let Some(due_at) = task.due_at {
+            text.push_str(&format!(" (due {})", to_local_date(&due_at)));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+pub fn to_ics(tasks: &[Task]) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//todo-CLI//EN"),
+    ];
+    for task in tasks {
+        lines.push(String::from("BEGIN:VTODO"));
+        lines.push(format!("UID:{}@todo-cli", task.uuid));
+        lines.push(format!("DTSTAMP:{}", ics_time(&task.created_at)));
+        lines.push(format!("SUMMARY:{}", escape_ics(&task.name)));
+        if let Some(due_at) = &task.due_at {
+            lines.push(format!("DUE:{}", ics_time(due_at)));
+        }
+        match &task.completed_at {
+            Some(completed_at) => {
+                lines.push(String::from("STATUS:COMPLETED"));
+                lines.push(format!("COMPLETED:{}", ics_time(completed_at)));
+            }
+            None => lines.push(String::from("STATUS:NEEDS-ACTION")),
+        }
+        lines.push(String::from("END:VTODO"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold_ics(&line));
+        ics.push_str("\r\n");
+    }
+    ics
+}
+
+fn ics_time(dt: &DateTime<Utc>) -> String {
+    dt.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Content lines longer than 75 octets are continued on the next line with a
+/// leading space, as required by RFC 5545.
+fn fold_ics(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// CSV stays in UTC so files read the same wherever they are opened.
+fn rfc3339(dt: &DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
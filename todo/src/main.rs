@@ -1,14 +1,34 @@
 use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Local;
+use chrono::Months;
+use chrono::NaiveDate;
 use chrono::Utc;
+use crossterm::cursor::Show;
 use crossterm::event;
 use crossterm::event::Event as CEvent;
 use crossterm::event::KeyCode;
+use crossterm::event::KeyModifiers;
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::execute;
 use crossterm::terminal::disable_raw_mode;
 use crossterm::terminal::enable_raw_mode;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
 use serde::{Deserialize, Serialize};
-use serde_json;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::fmt;
 use std::fs;
-use std::sync::mpsc;
+use std::hash::{Hash, Hasher};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant};
 use std::vec;
 use std::{io, thread};
@@ -17,7 +37,8 @@ use tui::style::Color;
 use tui::style::Modifier;
 use tui::text::{Span, Spans};
 use tui::widgets::{
-    Block, BorderType, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, Tabs,
+    Block, BorderType, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row,
+    Table, Tabs, Wrap,
 };
 use tui::{
     backend::CrosstermBackend,
@@ -25,452 +46,6404 @@ use tui::{
     style::Style,
     Terminal,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use uuid::Uuid;
+
+mod export;
 
 const DB_PATH: &str = "./data/db.json";
+const CONFIG_PATH: &str = "./data/config.toml";
+const STATE_FILE: &str = "state.json";
+const ARCHIVE_FILE: &str = "archive.json";
+const DB_VERSION: u32 = 3;
+const BACKUP_DIR: &str = "backups";
+const BACKUP_LIMIT: usize = 10;
+const LARGE_DB_WARNING: usize = 1000;
+/// How long the TUI waits after the last edit before writing it to disk.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+/// How quiet the task file must be before a change to it is reported.
+const FILE_CHANGE_DELAY: Duration = Duration::from_millis(200);
+/// Offered by the snooze menu, each picked with its number key.
+const SNOOZE_CHOICES: [(&str, i64); 4] = [
+    ("1 hour", 1),
+    ("1 day", 24),
+    ("3 days", 3 * 24),
+    ("1 week", 7 * 24),
+];
+const UNDO_LIMIT: usize = 20;
+const LOG_LIMIT: usize = 100;
+const MIN_POPUP_WIDTH: u16 = 30;
+const MIN_POPUP_HEIGHT: u16 = 5;
 
 #[derive(Serialize, Deserialize, Clone)]
 struct Task {
     id: usize,
+    /// Identifies the task across machines; `id` is only for display here.
+    #[serde(default = "Uuid::new_v4")]
+    uuid: Uuid,
     name: String,
     created_at: DateTime<Utc>,
     completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    priority: Priority,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    recurrence: Option<Recurrence>,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    parent_id: Option<usize>,
+    #[serde(default)]
+    estimate_minutes: Option<u32>,
+    #[serde(default)]
+    actual_minutes: Option<u32>,
+    #[serde(default)]
+    flag: Option<Flag>,
+    /// How long before `due_at` to be reminded, stored as seconds.
+    #[serde(
+        default,
+        serialize_with = "serialize_seconds",
+        deserialize_with = "deserialize_seconds"
+    )]
+    reminder_lead: Option<chrono::Duration>,
+    /// Hidden from the list until this arrives, unless deferred tasks are shown.
+    #[serde(default)]
+    start_at: Option<DateTime<Utc>>,
+    /// The recurring task this occurrence was queued up by.
+    #[serde(default)]
+    spawned_from: Option<Uuid>,
+}
+
+fn serialize_seconds<S: serde::Serializer>(
+    duration: &Option<chrono::Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration
+        .map(|duration| duration.num_seconds())
+        .serialize(serializer)
+}
+
+fn deserialize_seconds<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<chrono::Duration>, D::Error> {
+    Ok(Option::<i64>::deserialize(deserializer)?.map(chrono::Duration::seconds))
 }
 
 impl Task {
-    fn complete_task(mut self) -> Self {
-        self.completed_at = Some(Utc::now());
+    fn new(id: usize, name: &str) -> Self {
+        Task {
+            id,
+            uuid: Uuid::new_v4(),
+            name: name.to_string(),
+            created_at: Utc::now(),
+            completed_at: None,
+            due_at: None,
+            priority: Priority::default(),
+            tags: vec![],
+            recurrence: None,
+            notes: String::new(),
+            parent_id: None,
+            estimate_minutes: None,
+            actual_minutes: None,
+            flag: None,
+            reminder_lead: None,
+            start_at: None,
+            spawned_from: None,
+        }
+    }
+
+    fn toggle_completed(mut self) -> Self {
+        self.completed_at = match self.completed_at {
+            Some(_) => None,
+            None => Some(Utc::now()),
+        };
+        self
+    }
+
+    fn cycle_priority(mut self) -> Self {
+        self.priority = self.priority.next();
+        self
+    }
+
+    fn cycle_flag(mut self) -> Self {
+        self.flag = match self.flag {
+            None => Some(Flag::Red),
+            Some(Flag::Red) => Some(Flag::Orange),
+            Some(Flag::Orange) => Some(Flag::Green),
+            Some(Flag::Green) => Some(Flag::Blue),
+            Some(Flag::Blue) => None,
+        };
+        self
+    }
+
+    /// Pushes the due date back by `by`, or sets it `by` from `now` when the
+    /// task has none.
+    fn snooze(mut self, by: chrono::Duration, now: DateTime<Utc>) -> Self {
+        self.due_at = Some(self.due_at.unwrap_or(now) + by);
+        self
+    }
+
+    fn cycle_recurrence(mut self) -> Self {
+        self.recurrence = match self.recurrence {
+            None => Some(Recurrence::Daily),
+            Some(Recurrence::Daily) => Some(Recurrence::Weekly),
+            Some(Recurrence::Weekly) => Some(Recurrence::Monthly),
+            Some(Recurrence::Monthly) => None,
+        };
         self
     }
 }
 
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("Error reading the DB file {0}")]
-    ReadDBError(#[from] io::Error),
-    #[error("Error parsing the DB file {0}")]
-    ParseDBError(#[from] serde_json::Error),
+#[derive(Serialize, Deserialize)]
+struct Database {
+    version: u32,
+    next_id: usize,
+    tasks: Vec<Task>,
+    /// How the file was stored, so it is written back the same way.
+    #[serde(skip)]
+    format: DbFormat,
+    /// Set by the TUI so bursts of edits are written together by `flush_db`.
+    #[serde(skip)]
+    debounce: bool,
+    /// When the latest change not yet on disk was made, if there is one.
+    #[serde(skip)]
+    modified_at: Option<Instant>,
+    /// A hash of what was last written, so the watcher can tell our own
+    /// writes apart from changes made elsewhere.
+    #[serde(skip)]
+    written: std::cell::Cell<Option<u64>>,
 }
 
-enum Event<I> {
-    Input(I),
-    Tick,
+impl Default for Database {
+    fn default() -> Self {
+        Database {
+            version: DB_VERSION,
+            next_id: 0,
+            tasks: vec![],
+            format: DbFormat::Json,
+            debounce: false,
+            modified_at: None,
+            written: std::cell::Cell::new(None),
+        }
+    }
 }
 
-enum InputMode {
-    Normal,
-    Editing,
+impl Database {
+    /// Older databases are a bare array of tasks; the counter starts past the
+    /// highest id they contain so none of them is handed out again.
+    fn from_legacy(tasks: Vec<Task>) -> Self {
+        let next_id = tasks.iter().map(|task| task.id + 1).max().unwrap_or(0);
+        Database {
+            next_id,
+            tasks,
+            ..Database::default()
+        }
+    }
+
+    fn allocate_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
-enum MenuItem {
-    Home,
-    Tasks,
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+enum DbFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
 }
 
-impl From<MenuItem> for usize {
-    fn from(input: MenuItem) -> usize {
-        match input {
-            MenuItem::Home => 0,
-            MenuItem::Tasks => 1,
+impl DbFormat {
+    const NAMES: [&'static str; 3] = ["json", "toml", "yaml"];
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(DbFormat::Json),
+            "toml" => Some(DbFormat::Toml),
+            "yaml" | "yml" => Some(DbFormat::Yaml),
+            _ => None,
         }
     }
-}
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    enable_raw_mode()?;
-    let (tx, rx) = mpsc::channel();
-    let tick_rate = Duration::from_millis(200);
+    /// Picks the format from the file extension, falling back to JSON.
+    fn from_path(path: &str) -> Self {
+        Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(DbFormat::from_name)
+            .unwrap_or_default()
+    }
 
-    thread::spawn(move || {
-        let mut last_tick = Instant::now();
+    /// Reads the file into a JSON value so migrations see every format alike.
+    /// Errors are reported as JSON ones, which is what the recovery prompt
+    /// looks for.
+    fn deserialize(self, content: &str) -> Result<serde_json::Value, serde_json::Error> {
+        match self {
+            DbFormat::Json => serde_json::from_str(content),
+            DbFormat::Toml => toml::from_str(content).map_err(serde::de::Error::custom),
+            DbFormat::Yaml => serde_yaml::from_str(content).map_err(serde::de::Error::custom),
+        }
+    }
 
-        loop {
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_else(|| Duration::from_secs(0));
+    fn serialize(self, db: &Database) -> Result<Vec<u8>, serde_json::Error> {
+        match self {
+            DbFormat::Json => serde_json::to_vec(db),
+            DbFormat::Toml => toml::to_string(db)
+                .map(String::into_bytes)
+                .map_err(serde::ser::Error::custom),
+            DbFormat::Yaml => serde_yaml::to_string(db)
+                .map(String::into_bytes)
+                .map_err(serde::ser::Error::custom),
+        }
+    }
+}
 
-            if event::poll(timeout).expect("Poll works") {
-                if let CEvent::Key(key) = event::read().expect("Can read events") {
-                    tx.send(Event::Input(key)).expect("Can send events");
-                }
-            }
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+}
 
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Event::Tick) {
-                    last_tick = Instant::now();
-                }
-            }
+impl Recurrence {
+    /// Months are added on the calendar, so Jan 31 advances to the last day
+    /// of February rather than spilling into March.
+    fn advance(self, dt: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Recurrence::Daily => dt + chrono::Duration::days(1),
+            Recurrence::Weekly => dt + chrono::Duration::weeks(1),
+            Recurrence::Monthly => dt.checked_add_months(Months::new(1)).unwrap_or(dt),
         }
-    });
+    }
+}
 
-    let stdout = io::stdout();
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            Recurrence::Daily => "Daily",
+            Recurrence::Weekly => "Weekly",
+            Recurrence::Monthly => "Monthly",
+        };
+        write!(f, "{}", label)
+    }
+}
 
-    let menu_titles = vec!["Home", "Tasks"];
-    let mut show_pop_up = false;
-    let mut input_mode = InputMode::Normal;
-    let mut active_menu_item = MenuItem::Home;
-    let mut task_list_state = ListState::default();
-    task_list_state.select(Some(0));
-    let mut new_task = String::new();
+/// A color the user picks for a task, whatever its priority.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+enum Flag {
+    Red,
+    Orange,
+    Green,
+    Blue,
+}
 
-    loop {
-        terminal.draw(|rect| {
-            let size = rect.size();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Length(3),
-                        Constraint::Min(2),
-                        Constraint::Length(3),
-                    ]
-                    .as_ref(),
-                )
-                .split(size);
-
-            let copyright = Paragraph::new("todo-CLI 2023 - all rights reserved")
-                .style(Style::default().fg(Color::LightCyan))
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .style(Style::default().fg(Color::White))
-                        .title("Copyright")
-                        .border_type(BorderType::Plain),
-                );
-
-            rect.render_widget(copyright, chunks[2]);
-
-            let menu = menu_titles
-                .iter()
-                .map(|t| {
-                    let (first, rest) = t.split_at(1);
-                    Spans::from(vec![
-                        Span::styled(
-                            first,
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::UNDERLINED),
-                        ),
-                        Span::styled(rest, Style::default().fg(Color::White)),
-                    ])
-                })
-                .collect();
-
-            let tabs = Tabs::new(menu)
-                .select(active_menu_item.into())
-                .block(Block::default().title("Menu").borders(Borders::ALL))
-                .style(Style::default().fg(Color::White))
-                .highlight_style(Style::default().fg(Color::Yellow))
-                .divider(Span::raw("|"));
-
-            rect.render_widget(tabs, chunks[0]);
-
-            match active_menu_item {
-                MenuItem::Home => rect.render_widget(render_home(), chunks[1]),
-                MenuItem::Tasks => {
-                    let todo_chunks = Layout::default()
-                        .direction(Direction::Horizontal)
-                        .constraints(
-                            [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
-                        )
-                        .split(chunks[1]);
-                    let (left, right) = render_todo(&task_list_state);
-                    rect.render_stateful_widget(left, todo_chunks[0], &mut task_list_state);
-                    rect.render_widget(right, todo_chunks[1]);
-                }
-            }
-
-            let (cursor_x, cursor_y) = if show_pop_up {
-                let (block, area) = render_popup(size);
-                rect.render_widget(Clear, area);
-                rect.render_widget(block, area);
-
-                let input =
-                    Paragraph::new(new_task.as_ref()).style(Style::default().fg(Color::Cyan));
-                rect.render_widget(input, centered_rect(95, 60, area));
-                (area.x, area.y)
-            } else {
-                (0, 0)
-            };
+impl Flag {
+    fn color(self) -> Color {
+        match self {
+            Flag::Red => Color::Red,
+            Flag::Orange => Color::Rgb(255, 140, 0),
+            Flag::Green => Color::Green,
+            Flag::Blue => Color::Blue,
+        }
+    }
+}
 
-            match input_mode {
-                InputMode::Normal => {}
-                InputMode::Editing => {
-                    rect.set_cursor(cursor_x + new_task.len() as u16 + 2, cursor_y + 1)
-                }
-            }
-        })?;
+#[derive(Serialize, Deserialize, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
 
-        match rx.recv()? {
-            Event::Input(event) => match input_mode {
-                InputMode::Normal => match event.code {
-                    KeyCode::Char('q') => {
-                        disable_raw_mode()?;
-                        terminal.show_cursor()?;
-                        break;
-                    }
-                    KeyCode::Char('h') => active_menu_item = MenuItem::Home,
-                    KeyCode::Char('t') => active_menu_item = MenuItem::Tasks,
-                    KeyCode::Char('a') => {
-                        show_pop_up = true;
-                        input_mode = InputMode::Editing;
-                    }
-                    KeyCode::Char('d') => {
-                        remove_task_at_index(&mut task_list_state).unwrap_or_else(|_| ());
-                    }
-                    KeyCode::Char('c') => {
-                        complete_task(&mut task_list_state)?;
-                    }
-                    KeyCode::Down => {
-                        if let Some(selected) = task_list_state.selected() {
-                            let amount_tasks = read_db().expect("Can read db.").len();
-                            if selected >= amount_tasks - 1 {
-                                task_list_state.select(Some(0));
-                            } else {
-                                task_list_state.select(Some(selected + 1));
-                            }
-                        }
-                    }
-                    KeyCode::Up => {
-                        if let Some(selected) = task_list_state.selected() {
-                            let amount_tasks = read_db().expect("Can read db.").len();
-                            if selected > 0 {
-                                task_list_state.select(Some(selected - 1));
-                            } else {
-                                task_list_state.select(Some(amount_tasks - 1));
-                            }
-                        }
-                    }
-                    _ => {}
-                },
-                InputMode::Editing => match event.code {
-                    KeyCode::Enter => {
-                        add_task_to_db(&new_task)?;
-                        input_mode = InputMode::Normal;
-                        new_task = String::new();
-                        show_pop_up = false;
-                    }
-                    KeyCode::Char(c) => {
-                        new_task.push(c);
-                    }
-                    KeyCode::Backspace => {
-                        new_task.pop();
-                    }
-                    KeyCode::Esc => {
-                        input_mode = InputMode::Normal;
-                        new_task = String::new();
-                        show_pop_up = false;
-                    }
-                    _ => {}
-                },
-            },
-            Event::Tick => {}
+impl Priority {
+    fn next(self) -> Self {
+        match self {
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High => Priority::Low,
         }
     }
 
-    Ok(())
+    fn color(self) -> Color {
+        match self {
+            Priority::Low => Color::Green,
+            Priority::Medium => Color::Yellow,
+            Priority::High => Color::Red,
+        }
+    }
 }
 
-fn render_home<'a>() -> Paragraph<'a> {
-    let home = Paragraph::new(vec![
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("Welcome")]),
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("to")]),
-        Spans::from(vec![Span::styled(
-            "todo-CLI",
-            Style::default().fg(Color::LightBlue),
-        )]),
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("Press 't' top access the todo list")]),
-    ])
-    .alignment(Alignment::Center)
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Home")
-            .border_type(BorderType::Plain),
-    );
-    home
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        };
+        write!(f, "{}", label)
+    }
 }
 
-fn read_db() -> Result<Vec<Task>, Error> {
-    let db_content = fs::read_to_string(DB_PATH)?;
-    let parsed: Vec<Task> = serde_json::from_str(&db_content)?;
-    Ok(parsed)
+#[derive(Serialize, Deserialize, Default)]
+struct UiState {
+    selected: usize,
 }
 
-fn write_db(tasks: &Vec<Task>) -> Result<(), Error> {
-    fs::write(DB_PATH, &serde_json::to_vec(tasks)?)?;
-    Ok(())
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Error reading the DB file {0}")]
+    ReadDBError(#[from] io::Error),
+    #[error("Error parsing the DB file {0}")]
+    ParseDBError(#[from] serde_json::Error),
+    #[error("Task name cannot be empty")]
+    EmptyTaskName,
+    #[error("Error parsing the config file {0}")]
+    ParseConfigError(#[from] toml::de::Error),
+    #[error("Unknown key '{0}' in the config file")]
+    UnknownKey(String),
+    #[error("Tasks cannot be changed in read-only mode")]
+    ReadOnly,
+    #[error("Save the changes made here before reloading")]
+    UnsavedChanges,
+    #[error("Could not run the editor {0}")]
+    EditorFailed(String),
+    #[error("The edited file did not parse, so the previous version was kept: {0}")]
+    InvalidEdit(serde_json::Error),
+    #[error("Only tasks with a due date can have a reminder")]
+    NoDueDate,
+    #[error("Unknown format '{0}', expected one of: {names}", names = DbFormat::NAMES.join(", "))]
+    UnknownFormat(String),
+    #[error("Unknown theme '{0}', expected one of: {names}", names = Theme::NAMES.join(", "))]
+    UnknownTheme(String),
+    #[error("No task with id '{0}'")]
+    TaskNotFound(String),
+    #[error("Error using the clipboard {0}")]
+    ClipboardError(#[from] arboard::Error),
+    #[error("Stopped receiving input events")]
+    InputClosed(#[from] mpsc::RecvError),
 }
 
-fn render_todo<'a>(task_list_state: &ListState) -> (List<'a>, Table<'a>) {
-    let tasks = Block::default()
-        .borders(Borders::ALL)
-        .style(Style::default().fg(Color::White))
-        .title("Todo list")
-        .border_type(BorderType::Plain);
+#[derive(Copy, Clone)]
+struct Theme {
+    fg: Color,
+    accent: Color,
+    highlight_fg: Color,
+    highlight_bg: Color,
+    title: Color,
+    info: Color,
+    input: Color,
+    error: Color,
+}
 
-    let task_list = read_db().expect("Can fetch task list");
-    let items: Vec<_> = task_list
-        .iter()
-        .map(|task| {
-            ListItem::new(Spans::from(vec![Span::styled(
-                task.name.clone(),
-                match task.completed_at {
-                    Some(_) => Style::default().fg(Color::Green),
-                    None => Style::default(),
-                },
-            )]))
-        })
-        .collect();
+impl Theme {
+    const NAMES: &'static [&'static str] = &["dark", "light", "solarized"];
 
-    let selected_task = match task_list.get(
-        task_list_state
-            .selected()
-            .expect("There is always a selected task."),
-    ) {
-        Some(task) => task.clone(),
-        None => Task {
-            id: 0,
-            name: String::from("Create some tasks!"),
-            created_at: Utc::now(),
-            completed_at: None,
-        },
-    };
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "solarized" => Some(Theme::solarized()),
+            _ => None,
+        }
+    }
 
-    let list = List::new(items).block(tasks).highlight_style(
-        Style::default()
-            .bg(Color::Yellow)
-            .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
-    );
+    fn dark() -> Self {
+        Theme {
+            fg: Color::White,
+            accent: Color::Yellow,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::Yellow,
+            title: Color::LightBlue,
+            info: Color::LightCyan,
+            input: Color::Cyan,
+            error: Color::LightRed,
+        }
+    }
 
-    let task_detail = Table::new(vec![Row::new(vec![
-        Cell::from(Span::raw(selected_task.id.to_string())),
-        Cell::from(Span::raw(selected_task.name)),
-        Cell::from(Span::raw(selected_task.created_at.to_string())),
-        Cell::from(Span::raw(match selected_task.completed_at {
-            Some(completed_at) => completed_at.to_string(),
-            None => "".to_string(),
-        })),
-    ])])
-    .header(Row::new(vec![
-        Cell::from(Span::styled(
-            "ID",
-            Style::default().add_modifier(Modifier::BOLD),
-        )),
-        Cell::from(Span::styled(
-            "Name",
-            Style::default().add_modifier(Modifier::BOLD),
-        )),
-        Cell::from(Span::styled(
-            "Created At",
-            Style::default().add_modifier(Modifier::BOLD),
-        )),
-        Cell::from(Span::styled(
-            "Completed At",
-            Style::default().add_modifier(Modifier::BOLD),
-        )),
-    ]))
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Detail")
-            .border_type(BorderType::Plain),
-    )
-    .widths(&[
-        Constraint::Percentage(8),
-        Constraint::Percentage(23),
-        Constraint::Percentage(23),
-        Constraint::Percentage(23),
-    ]);
-    (list, task_detail)
-}
+    fn light() -> Self {
+        Theme {
+            fg: Color::Black,
+            accent: Color::Blue,
+            highlight_fg: Color::White,
+            highlight_bg: Color::Blue,
+            title: Color::Magenta,
+            info: Color::DarkGray,
+            input: Color::Blue,
+            error: Color::Red,
+        }
+    }
 
-fn add_task_to_db(task_name: &str) -> Result<Vec<Task>, Error> {
-    let mut parsed = read_db()?;
+    fn solarized() -> Self {
+        Theme {
+            fg: Color::Rgb(147, 161, 161),
+            accent: Color::Rgb(181, 137, 0),
+            highlight_fg: Color::Rgb(0, 43, 54),
+            highlight_bg: Color::Rgb(38, 139, 210),
+            title: Color::Rgb(42, 161, 152),
+            info: Color::Rgb(108, 113, 196),
+            input: Color::Rgb(38, 139, 210),
+            error: Color::Rgb(220, 50, 47),
+        }
+    }
+}
 
-    let new_id = match parsed.last() {
-        Some(task) => task.id + 1,
-        None => 0,
-    };
+enum Command {
+    Add(String),
+    List,
+    Backups,
+    Restore(String),
+    ImportTxt(String),
+    Done(String),
+    Remove(String),
+}
 
-    parsed.push(Task {
-        id: new_id,
-        name: task_name.to_string(),
-        created_at: Utc::now(),
-        completed_at: None,
-    });
-    write_db(&parsed)?;
-    Ok(parsed)
+struct Args {
+    db_path: String,
+    command: Option<Command>,
+    format: Option<String>,
+    export_csv: Option<String>,
+    export_md: Option<String>,
+    export_ics: Option<String>,
+    import: Option<String>,
+    theme: Option<String>,
+    wrap_navigation: Option<bool>,
+    read_only: bool,
+    notify: bool,
+    tick_ms: Option<u64>,
+    daily_goal: Option<usize>,
 }
 
-fn remove_task_at_index(task_list_state: &mut ListState) -> Result<(), Error> {
-    if let Some(selected) = task_list_state.selected() {
-        let mut parsed = read_db()?;
-        parsed.remove(selected);
-        write_db(&parsed)?;
-        match selected {
-            0 => task_list_state.select(Some(0)),
-            _ => task_list_state.select(Some(selected - 1)),
+impl Args {
+    fn from_env() -> Self {
+        let mut args = Args {
+            db_path: DB_PATH.to_string(),
+            command: None,
+            format: None,
+            export_csv: None,
+            export_md: None,
+            export_ics: None,
+            import: None,
+            theme: None,
+            wrap_navigation: None,
+            read_only: false,
+            notify: false,
+            tick_ms: None,
+            daily_goal: None,
         };
+        let mut argv = env::args().skip(1);
+        while let Some(arg) = argv.next() {
+            match arg.as_str() {
+                "--db" => {
+                    if let Some(path) = argv.next() {
+                        args.db_path = path;
+                    }
+                }
+                "--format" => args.format = argv.next(),
+                "--export-csv" => args.export_csv = argv.next(),
+                "--export-md" => args.export_md = argv.next(),
+                "--export-ics" => args.export_ics = argv.next(),
+                "--import" => args.import = argv.next(),
+                "--theme" => args.theme = argv.next(),
+                "--wrap" => args.wrap_navigation = Some(true),
+                "--no-wrap" => args.wrap_navigation = Some(false),
+                "--read-only" => args.read_only = true,
+                "--notify" => args.notify = true,
+                "--tick-ms" => args.tick_ms = argv.next().and_then(|value| value.parse().ok()),
+                "--goal" => args.daily_goal = argv.next().and_then(|value| value.parse().ok()),
+                "add" if args.command.is_none() => {
+                    args.command = Some(Command::Add(argv.next().unwrap_or_default()))
+                }
+                "list" if args.command.is_none() => args.command = Some(Command::List),
+                "backups" if args.command.is_none() => args.command = Some(Command::Backups),
+                "restore" if args.command.is_none() => {
+                    args.command = Some(Command::Restore(argv.next().unwrap_or_default()))
+                }
+                "import-txt" if args.command.is_none() => {
+                    args.command = Some(Command::ImportTxt(argv.next().unwrap_or_default()))
+                }
+                "done" if args.command.is_none() => {
+                    args.command = Some(Command::Done(argv.next().unwrap_or_default()))
+                }
+                "rm" if args.command.is_none() => {
+                    args.command = Some(Command::Remove(argv.next().unwrap_or_default()))
+                }
+                _ => {}
+            }
+        }
+        args
     }
-    Ok(())
 }
 
-fn complete_task(task_list_state: &mut ListState) -> Result<(), Error> {
-    if let Some(selected) = task_list_state.selected() {
-        let mut parsed = read_db()?;
-        let task = match parsed.get(selected) {
-            Some(e) => e.clone().complete_task(),
-            _ => return Ok(()),
-        };
+#[derive(Deserialize)]
+#[serde(default)]
+struct Config {
+    theme: String,
+    tick_ms: u64,
+    wrap_navigation: bool,
+    default_filter: TaskFilter,
+    daily_goal: Option<usize>,
+    /// Typing a character no command uses starts adding a task with it.
+    quick_add: bool,
+    /// Set once the first-run tutorial has been dismissed.
+    tutorial_seen: bool,
+    /// Draws the Tasks tab without borders to fit more tasks.
+    dense: bool,
+    /// Lists completed tasks after open ones, whatever the sort order.
+    sink_completed: bool,
+    keys: HashMap<Action, String>,
+}
 
-        parsed.remove(selected);
-        parsed.insert(selected, task);
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            theme: String::from("dark"),
+            tick_ms: 200,
+            wrap_navigation: true,
+            default_filter: TaskFilter::All,
+            daily_goal: None,
+            quick_add: false,
+            tutorial_seen: false,
+            dense: false,
+            sink_completed: false,
+            keys: HashMap::new(),
+        }
+    }
+}
 
-        write_db(&parsed)?;
+impl Config {
+    fn override_with(&mut self, args: &Args) {
+        if let Some(theme) = &args.theme {
+            self.theme = theme.clone();
+        }
+        if let Some(tick_ms) = args.tick_ms {
+            self.tick_ms = tick_ms;
+        }
+        if let Some(wrap_navigation) = args.wrap_navigation {
+            self.wrap_navigation = wrap_navigation;
+        }
+        if args.daily_goal.is_some() {
+            self.daily_goal = args.daily_goal;
+        }
     }
-    Ok(())
 }
 
-fn render_popup<'a>(size: Rect) -> (Block<'a>, Rect) {
-    let block = Block::default().title("Add task").borders(Borders::ALL);
-    let area = centered_rect(60, 20, size);
-    (block, area)
+fn load_config() -> Result<Config, Error> {
+    if !Path::new(CONFIG_PATH).exists() {
+        return Ok(Config::default());
+    }
+    Ok(toml::from_str(&fs::read_to_string(CONFIG_PATH)?)?)
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, rect: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-                Constraint::Percentage((100 - percent_y) / 2),
-                Constraint::Percentage(percent_y),
-                Constraint::Percentage((100 - percent_y) / 2),
-            ]
-            .as_ref(),
-        )
-        .split(rect);
-
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(
-            [
-                Constraint::Percentage((100 - percent_x) / 2),
-                Constraint::Percentage(percent_x),
-                Constraint::Percentage((100 - percent_x) / 2),
-            ]
-            .as_ref(),
-        )
-        .split(popup_layout[1])[1]
+/// Sets a top-level `key = value` in the config file, leaving the rest of it
+/// as the user wrote it.
+fn set_config_value(key: &str, value: &str) -> Result<(), Error> {
+    let path = Path::new(CONFIG_PATH);
+    let content = if path.exists() {
+        fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let setting = format!("{} = {}", key, value);
+    // Top-level keys have to come before any table.
+    let top_level = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    match lines[..top_level]
+        .iter_mut()
+        .find(|line| line.split('=').next().map(str::trim) == Some(key))
+    {
+        Some(line) => *line = setting,
+        None => lines.insert(0, setting),
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomically(path, format!("{}\n", lines.join("\n")).as_bytes())
+}
+
+#[derive(Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    ShowHome,
+    ShowTasks,
+    ShowStats,
+    ShowArchive,
+    PreviousTab,
+    ToggleSplit,
+    SelectUp,
+    SelectDown,
+    SelectFirst,
+    SelectLast,
+    PageUp,
+    PageDown,
+    MoveTaskUp,
+    MoveTaskDown,
+    AddTask,
+    AddSubtask,
+    EditTask,
+    DuplicateTask,
+    EditNotes,
+    Complete,
+    CyclePriority,
+    CycleFlag,
+    CycleRecurrence,
+    Snooze,
+    SetReminder,
+    Defer,
+    Mark,
+    DeleteTask,
+    Undo,
+    ArchiveTask,
+    RestoreTask,
+    ClearCompleted,
+    ToggleTimer,
+    CycleSort,
+    ToggleAbsoluteTimes,
+    ToggleAgeColors,
+    ToggleDense,
+    ToggleDeferred,
+    ToggleSinkCompleted,
+    CycleFilter,
+    TodayFilter,
+    DateRange,
+    CycleTag,
+    Search,
+    ClearSearch,
+    ShowLog,
+    Focus,
+    CopyName,
+    Save,
+    Reload,
+    EditExternally,
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Whether the action changes the task list; these are refused in
+    /// read-only mode.
+    fn mutates(self) -> bool {
+        matches!(
+            self,
+            Action::MoveTaskUp
+                | Action::MoveTaskDown
+                | Action::AddTask
+                | Action::AddSubtask
+                | Action::EditTask
+                | Action::DuplicateTask
+                | Action::EditNotes
+                | Action::Complete
+                | Action::CyclePriority
+                | Action::CycleFlag
+                | Action::CycleRecurrence
+                | Action::Snooze
+                | Action::SetReminder
+                | Action::Defer
+                | Action::DeleteTask
+                | Action::Undo
+                | Action::ArchiveTask
+                | Action::RestoreTask
+                | Action::ClearCompleted
+                | Action::ToggleTimer
+                | Action::EditExternally
+        )
+    }
+
+    /// Actions on the selected task only apply on the Tasks tab, where that
+    /// task can be seen.
+    fn applies_in(self, menu_item: MenuItem) -> bool {
+        menu_item == MenuItem::Tasks
+            || !matches!(
+                self,
+                Action::MoveTaskUp
+                    | Action::MoveTaskDown
+                    | Action::AddSubtask
+                    | Action::EditTask
+                    | Action::DuplicateTask
+                    | Action::EditNotes
+                    | Action::Complete
+                    | Action::CyclePriority
+                    | Action::CycleFlag
+                    | Action::CycleRecurrence
+                    | Action::Snooze
+                    | Action::SetReminder
+                    | Action::Defer
+                    | Action::Mark
+                    | Action::DeleteTask
+                    | Action::ArchiveTask
+                    | Action::ToggleTimer
+                    | Action::CopyName
+            )
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Action::ShowHome => "Show the home screen",
+            Action::ShowTasks => "Show the todo list",
+            Action::ShowStats => "Show task statistics",
+            Action::ShowArchive => "Show archived tasks",
+            Action::PreviousTab => "Switch back to the previous tab, or column when split",
+            Action::ToggleSplit => "Split tasks into active and completed columns",
+            Action::SelectUp => "Move the selection up",
+            Action::SelectDown => "Move the selection down",
+            Action::SelectFirst => "Jump to the first task",
+            Action::SelectLast => "Jump to the last task",
+            Action::PageUp => "Show the previous page of tasks",
+            Action::PageDown => "Show the next page of tasks",
+            Action::MoveTaskUp => "Move the selected task up",
+            Action::MoveTaskDown => "Move the selected task down",
+            Action::AddTask => "Add a task (Tab switches fields)",
+            Action::AddSubtask => "Add a subtask to the selected task",
+            Action::EditTask => "Edit the selected task's name",
+            Action::DuplicateTask => "Duplicate the selected task",
+            Action::EditNotes => "Edit the selected task's notes",
+            Action::Complete => "Toggle completion (completes marked tasks)",
+            Action::CyclePriority => "Cycle the selected task's priority",
+            Action::CycleFlag => "Cycle the selected task's color flag",
+            Action::CycleRecurrence => "Cycle the selected task's recurrence",
+            Action::Snooze => "Push the selected task's due date back",
+            Action::SetReminder => "Set how long before it is due to be reminded",
+            Action::Defer => "Hide the selected task until a start date",
+            Action::Mark => "Mark the selected task for bulk completion/deletion",
+            Action::DeleteTask => "Delete the selected (or marked) tasks",
+            Action::Undo => "Undo the last deletion",
+            Action::ArchiveTask => "Archive the selected completed task",
+            Action::RestoreTask => "Restore the selected archived task",
+            Action::ClearCompleted => "Archive all completed tasks",
+            Action::ToggleTimer => "Start/stop timing the selected task",
+            Action::CycleSort => "Cycle the sort order",
+            Action::ToggleAbsoluteTimes => "Toggle relative/absolute created times",
+            Action::ToggleAgeColors => "Toggle coloring open tasks by age/priority",
+            Action::ToggleDense => "Toggle the compact, borderless task list",
+            Action::ToggleDeferred => "Show or hide tasks that have not started yet",
+            Action::ToggleSinkCompleted => "Toggle listing completed tasks last",
+            Action::CycleFilter => "Cycle all/active/completed filter",
+            Action::TodayFilter => "Show tasks due today and overdue",
+            Action::DateRange => "Show tasks created within a date range",
+            Action::CycleTag => "Cycle the tag filter",
+            Action::Search => "Search task names and notes",
+            Action::ClearSearch => "Clear the search",
+            Action::ShowLog => "Show the message log",
+            Action::Focus => "Show only the selected task",
+            Action::CopyName => "Copy the selected task name to the clipboard",
+            Action::Save => "Save changes now",
+            Action::Reload => "Reload the task file from disk",
+            Action::EditExternally => "Edit the task file in $EDITOR",
+            Action::Help => "Toggle this help",
+            Action::Quit => "Quit",
+        }
+    }
+}
+
+/// Keys for each action, in the order the help overlay lists them.
+struct KeyBindings {
+    bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use KeyCode::Char;
+        KeyBindings {
+            bindings: vec![
+                (Action::ShowHome, vec![Char('h'), Char('1')]),
+                (Action::ShowTasks, vec![Char('t'), Char('2')]),
+                (Action::ShowStats, vec![Char('3')]),
+                (Action::ShowArchive, vec![Char('4')]),
+                (Action::PreviousTab, vec![KeyCode::Tab]),
+                (Action::ToggleSplit, vec![Char('V')]),
+                (Action::SelectUp, vec![KeyCode::Up]),
+                (Action::SelectDown, vec![KeyCode::Down]),
+                (Action::SelectFirst, vec![Char('g'), KeyCode::Home]),
+                (Action::SelectLast, vec![Char('G'), KeyCode::End]),
+                (Action::PageUp, vec![KeyCode::PageUp]),
+                (Action::PageDown, vec![KeyCode::PageDown]),
+                (Action::MoveTaskUp, vec![Char('K')]),
+                (Action::MoveTaskDown, vec![Char('J')]),
+                (Action::AddTask, vec![Char('a')]),
+                (Action::AddSubtask, vec![Char('S')]),
+                (Action::EditTask, vec![Char('e')]),
+                (Action::DuplicateTask, vec![Char('y')]),
+                (Action::EditNotes, vec![Char('n')]),
+                (Action::Complete, vec![Char('c'), KeyCode::Enter]),
+                (Action::CyclePriority, vec![Char('p')]),
+                (Action::CycleFlag, vec![Char('b')]),
+                (Action::CycleRecurrence, vec![Char('R')]),
+                (Action::Snooze, vec![Char('z')]),
+                (Action::SetReminder, vec![Char('L')]),
+                (Action::Defer, vec![Char('H')]),
+                (Action::Mark, vec![Char(' ')]),
+                (Action::DeleteTask, vec![Char('d')]),
+                (Action::Undo, vec![Char('u')]),
+                (Action::ArchiveTask, vec![Char('A')]),
+                (Action::RestoreTask, vec![Char('U')]),
+                (Action::ClearCompleted, vec![Char('C')]),
+                (Action::ToggleTimer, vec![Char('i')]),
+                (Action::CycleSort, vec![Char('s')]),
+                (Action::ToggleAbsoluteTimes, vec![Char('T')]),
+                (Action::ToggleAgeColors, vec![Char('O')]),
+                (Action::ToggleDense, vec![Char('M')]),
+                (Action::ToggleDeferred, vec![Char('Z')]),
+                (Action::ToggleSinkCompleted, vec![Char('B')]),
+                (Action::CycleFilter, vec![Char('f')]),
+                (Action::TodayFilter, vec![Char('D')]),
+                (Action::DateRange, vec![Char('W')]),
+                (Action::CycleTag, vec![Char('#')]),
+                (Action::Search, vec![Char('/')]),
+                (Action::ClearSearch, vec![KeyCode::Esc]),
+                (Action::ShowLog, vec![Char('l')]),
+                (Action::Focus, vec![Char('F')]),
+                (Action::CopyName, vec![Char('Y')]),
+                (Action::Save, vec![Char('w')]),
+                (Action::Reload, vec![Char('r')]),
+                (Action::EditExternally, vec![Char('E')]),
+                (Action::Help, vec![Char('?')]),
+                (Action::Quit, vec![Char('q')]),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Starts from the defaults and rebinds each configured action to its
+    /// key, which is taken away from whichever action had it before.
+    fn from_config(keys: &HashMap<Action, String>) -> Result<Self, Error> {
+        let mut key_bindings = KeyBindings::default();
+        for (&action, name) in keys {
+            let code = parse_key(name).ok_or_else(|| Error::UnknownKey(name.clone()))?;
+            for (bound, codes) in &mut key_bindings.bindings {
+                if *bound == action {
+                    *codes = vec![code];
+                } else {
+                    codes.retain(|&existing| existing != code);
+                }
+            }
+        }
+        Ok(key_bindings)
+    }
+
+    fn action(&self, code: KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, codes)| codes.contains(&code))
+            .map(|&(action, _)| action)
+    }
+
+    /// The keys bound to `action`, as shown to the user.
+    fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == action)
+            .map(|(_, codes)| {
+                let keys: Vec<String> = codes.iter().map(|&code| key_label(code)).collect();
+                keys.join(" / ")
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let code = match name {
+        "Space" => KeyCode::Char(' '),
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+    };
+    Some(code)
+}
+
+fn key_label(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::PageUp => String::from("PageUp"),
+        KeyCode::PageDown => String::from("PageDown"),
+        code => format!("{:?}", code),
+    }
+}
+
+struct LogEntry {
+    at: DateTime<Local>,
+    message: String,
+    is_error: bool,
+}
+
+fn push_log(log: &mut VecDeque<LogEntry>, message: &str, is_error: bool) {
+    if log.len() >= LOG_LIMIT {
+        log.pop_front();
+    }
+    log.push_back(LogEntry {
+        at: Local::now(),
+        message: message.to_string(),
+        is_error,
+    });
+}
+
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), DisableMouseCapture, Show);
+}
+
+enum Event<I> {
+    Input(I),
+    Mouse(MouseEvent),
+    Resize,
+    Tick,
+    /// The task file changed on disk.
+    FileChanged,
+}
+
+enum InputMode {
+    Normal,
+    Editing,
+    Searching,
+    ConfirmDelete,
+    ConfirmClear,
+    Help,
+    Notes,
+    RecoverDb,
+    Reminders,
+    Log,
+    Focus,
+    DateRange,
+    Tutorial,
+    Snooze,
+    ReminderLead,
+    StartDate,
+}
+
+impl InputMode {
+    fn label(&self) -> &'static str {
+        match self {
+            InputMode::Normal => "NORMAL",
+            InputMode::Editing => "EDITING",
+            InputMode::Searching => "SEARCH",
+            InputMode::ConfirmDelete | InputMode::ConfirmClear => "CONFIRM",
+            InputMode::Help => "HELP",
+            InputMode::Notes => "NOTES",
+            InputMode::RecoverDb => "RECOVER",
+            InputMode::Reminders => "REMINDERS",
+            InputMode::Log => "LOG",
+            InputMode::Focus => "FOCUS",
+            InputMode::DateRange => "DATES",
+            InputMode::Tutorial => "TUTORIAL",
+            InputMode::Snooze => "SNOOZE",
+            InputMode::ReminderLead => "REMINDER",
+            InputMode::StartDate => "DEFER",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum PopupField {
+    Name,
+    Due,
+    Estimate,
+}
+
+#[derive(Copy, Clone, PartialEq, Default)]
+enum SortMode {
+    #[default]
+    None,
+    CreatedAsc,
+    CreatedDesc,
+    Completed,
+    Priority,
+}
+
+impl SortMode {
+    fn next(self) -> Self {
+        match self {
+            SortMode::None => SortMode::CreatedAsc,
+            SortMode::CreatedAsc => SortMode::CreatedDesc,
+            SortMode::CreatedDesc => SortMode::Completed,
+            SortMode::Completed => SortMode::Priority,
+            SortMode::Priority => SortMode::None,
+        }
+    }
+
+    fn label(self) -> Option<&'static str> {
+        match self {
+            SortMode::None => None,
+            SortMode::CreatedAsc => Some("by created ↑"),
+            SortMode::CreatedDesc => Some("by created ↓"),
+            SortMode::Completed => Some("by completed"),
+            SortMode::Priority => Some("by priority"),
+        }
+    }
+}
+
+#[derive(Deserialize, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+enum TaskFilter {
+    #[default]
+    All,
+    Active,
+    Completed,
+    Today,
+}
+
+impl TaskFilter {
+    fn next(self) -> Self {
+        match self {
+            TaskFilter::All => TaskFilter::Active,
+            TaskFilter::Active => TaskFilter::Completed,
+            TaskFilter::Completed | TaskFilter::Today => TaskFilter::All,
+        }
+    }
+
+    fn label(self) -> Option<&'static str> {
+        match self {
+            TaskFilter::All => None,
+            TaskFilter::Active => Some("active"),
+            TaskFilter::Completed => Some("completed"),
+            TaskFilter::Today => Some("today"),
+        }
+    }
+
+    fn matches(self, task: &Task) -> bool {
+        match self {
+            TaskFilter::All => true,
+            TaskFilter::Active => task.completed_at.is_none(),
+            TaskFilter::Completed => task.completed_at.is_some(),
+            TaskFilter::Today => is_due_today(task, Local::now()),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct ViewOptions {
+    sort_mode: SortMode,
+    task_filter: TaskFilter,
+    filter: String,
+    tag: Option<String>,
+    absolute_times: bool,
+    age_colors: bool,
+    /// Only tasks created within this range are shown.
+    created: Option<DateRange>,
+    dense: bool,
+    show_deferred: bool,
+    sink_completed: bool,
+}
+
+/// Times from `from` up to but not including `to`, either of which may be
+/// left open.
+#[derive(Clone, Debug, PartialEq)]
+struct DateRange {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+impl DateRange {
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| at >= from) && self.to.is_none_or(|to| at < to)
+    }
+
+    /// The range as it is typed in, e.g. `2024-01-01..2024-01-07`.
+    fn label(&self) -> String {
+        let bound = |at: Option<DateTime<Utc>>| at.map(|at| to_local_date(&at)).unwrap_or_default();
+        let last_day = self.to.map(|to| to - chrono::Duration::nanoseconds(1));
+        format!("{}..{}", bound(self.from), bound(last_day))
+    }
+}
+
+/// Reads `YYYY-MM-DD..YYYY-MM-DD` as local days, inclusive at both ends.
+/// Either side may be left out; an empty input clears the range.
+fn parse_date_range(input: &str) -> Result<Option<DateRange>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || format!("Invalid range '{}', expected YYYY-MM-DD..YYYY-MM-DD", input);
+    let (from, to) = input.split_once("..").ok_or_else(invalid)?;
+    // The end day is included by stopping at the midnight after it.
+    let bound = |text: &str, days_after| -> Result<_, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.checked_add_days(chrono::Days::new(days_after)))
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .and_then(|at| at.and_local_timezone(Local).earliest())
+            .map(|at| Some(at.with_timezone(&Utc)))
+            .ok_or_else(invalid)
+    };
+    let range = DateRange {
+        from: bound(from, 0)?,
+        to: bound(to, 1)?,
+    };
+    match (range.from, range.to) {
+        (Some(from), Some(to)) if from >= to => Err(invalid()),
+        _ => Ok(Some(range)),
+    }
+}
+
+/// The Tasks pane split into an active and a completed column. The focused
+/// column uses the usual selection and filter; this keeps the other one.
+struct SplitView {
+    filter_before: TaskFilter,
+    other_state: ListState,
+}
+
+/// The column of the split view not showing `focused`.
+fn other_column(focused: TaskFilter) -> TaskFilter {
+    match focused {
+        TaskFilter::Active => TaskFilter::Completed,
+        _ => TaskFilter::Active,
+    }
+}
+
+fn notes_match(task: &Task, query: &str) -> bool {
+    task.notes.to_lowercase().contains(&query.to_lowercase())
+}
+
+impl ViewOptions {
+    /// Match score for the search query, or `None` if the task is hidden.
+    /// Names match fuzzily; notes are too long for that to mean much, so
+    /// they need the query as a substring and rank below any name match.
+    fn score(&self, matcher: &SkimMatcherV2, task: &Task) -> Option<i64> {
+        if !self.task_filter.matches(task)
+            || (!self.show_deferred && is_deferred(task, Utc::now()))
+            || !self.tag.as_ref().is_none_or(|tag| task.tags.contains(tag))
+            || !self
+                .created
+                .as_ref()
+                .is_none_or(|range| range.contains(task.created_at))
+        {
+            return None;
+        }
+        if self.filter.is_empty() {
+            return Some(0);
+        }
+        matcher
+            .fuzzy_match(&task.name, &self.filter)
+            .or_else(|| notes_match(task, &self.filter).then_some(0))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum MenuItem {
+    Home,
+    Tasks,
+    Stats,
+    Archive,
+}
+
+impl From<MenuItem> for usize {
+    fn from(input: MenuItem) -> usize {
+        match input {
+            MenuItem::Home => 0,
+            MenuItem::Tasks => 1,
+            MenuItem::Stats => 2,
+            MenuItem::Archive => 3,
+        }
+    }
+}
+
+fn main() {
+    // Printed with Display, as returning the error from `main` would print
+    // its Debug form.
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    // The guard only runs once a panic has unwound, so without this the
+    // message would be printed to a terminal still in raw mode. Panics on the
+    // input and watcher threads never reach it at all.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+
+    let args = Args::from_env();
+    let db_path = args.db_path.as_str();
+    let format = match &args.format {
+        Some(name) => {
+            DbFormat::from_name(name).ok_or_else(|| Error::UnknownFormat(name.clone()))?
+        }
+        None => DbFormat::from_path(db_path),
+    };
+    let first_run = !Path::new(db_path).exists();
+    if !args.read_only {
+        init_db(db_path, format)?;
+    }
+
+    if let Some(path) = &args.export_csv {
+        let tasks = read_db(db_path, format)?.tasks;
+        fs::write(path, export::to_csv(&tasks))?;
+        println!("Exported {} tasks to {}", tasks.len(), path);
+        return Ok(());
+    }
+
+    if let Some(path) = &args.export_md {
+        let tasks = read_db(db_path, format)?.tasks;
+        fs::write(path, export::to_markdown(&tasks))?;
+        println!("Exported {} tasks to {}", tasks.len(), path);
+        return Ok(());
+    }
+
+    if let Some(path) = &args.export_ics {
+        let tasks = read_db(db_path, format)?.tasks;
+        fs::write(path, export::to_ics(&tasks))?;
+        println!("Exported {} tasks to {}", tasks.len(), path);
+        return Ok(());
+    }
+
+    if args.read_only
+        && (args.import.is_some()
+            || matches!(
+                args.command,
+                Some(
+                    Command::Add(_)
+                        | Command::Restore(_)
+                        | Command::ImportTxt(_)
+                        | Command::Done(_)
+                        | Command::Remove(_)
+                )
+            ))
+    {
+        return Err(Error::ReadOnly);
+    }
+
+    if let Some(path) = &args.import {
+        let (incoming, version) = parse_db(&fs::read_to_string(path)?, DbFormat::from_path(path))?;
+        let mut db = read_db(db_path, format)?;
+        let imported = merge_tasks(&mut db, incoming.tasks, version < 3);
+        write_db(db_path, &db)?;
+        println!("Imported {} tasks from {}", imported, path);
+        return Ok(());
+    }
+
+    match &args.command {
+        Some(Command::Add(name)) => {
+            let mut db = read_db(db_path, format)?;
+            add_task_to_db(db_path, &mut db, name, None, None, None)?;
+            if let Some(task) = db.tasks.last() {
+                println!("{}", task.id);
+            }
+            return Ok(());
+        }
+        Some(Command::ImportTxt(path)) => {
+            let mut db = read_db(db_path, format)?;
+            let imported = add_lines(&mut db, &fs::read_to_string(path)?);
+            write_db(db_path, &db)?;
+            println!("Imported {} tasks from {}", imported, path);
+            return Ok(());
+        }
+        Some(Command::Done(id)) => {
+            let mut db = read_db(db_path, format)?;
+            let task = find_task(&db.tasks, id)?.clone();
+            complete_tasks(db_path, &mut db, &HashSet::from([task.id]))?;
+            println!("Completed {}", task.name);
+            return Ok(());
+        }
+        Some(Command::Remove(id)) => {
+            let mut db = read_db(db_path, format)?;
+            let task = find_task(&db.tasks, id)?.clone();
+            let removed = remove_tasks(db_path, &mut db, &HashSet::from([task.id]))?;
+            match removed.len() {
+                1 => println!("Removed {}", task.name),
+                n => println!("Removed {} and {} subtasks", task.name, n - 1),
+            }
+            return Ok(());
+        }
+        Some(Command::List) => {
+            print!("{}", export::to_text(&read_db(db_path, format)?.tasks));
+            return Ok(());
+        }
+        Some(Command::Backups) => {
+            for backup in list_backups(db_path)?.iter().rev() {
+                if let Some(name) = backup.file_name() {
+                    println!("{}", name.to_string_lossy());
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Restore(name)) => {
+            let backup = backup_dir(db_path).join(name);
+            let (backup_db, _) =
+                parse_db(&fs::read_to_string(&backup)?, DbFormat::from_path(name))?;
+            let db = Database {
+                format,
+                ..backup_db
+            };
+            write_db(db_path, &db)?;
+            println!(
+                "Restored {} tasks from {}",
+                db.tasks.len(),
+                backup.display()
+            );
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let mut config = load_config()?;
+    config.override_with(&args);
+    let theme =
+        Theme::from_name(&config.theme).ok_or_else(|| Error::UnknownTheme(config.theme.clone()))?;
+    let key_bindings = KeyBindings::from_config(&config.keys)?;
+
+    enable_raw_mode()?;
+    let _terminal_guard = TerminalGuard;
+    execute!(io::stdout(), EnableMouseCapture)?;
+    let (tx, rx) = mpsc::channel();
+    let watch_tx = tx.clone();
+    let tick_rate = Duration::from_millis(config.tick_ms);
+    // Set while an external editor has the terminal, so its keys go there.
+    let input_paused = Arc::new(AtomicBool::new(false));
+    let thread_paused = Arc::clone(&input_paused);
+
+    thread::spawn(move || {
+        let mut last_tick = Instant::now();
+
+        loop {
+            if thread_paused.load(Ordering::SeqCst) {
+                thread::sleep(tick_rate);
+                continue;
+            }
+            let timeout = tick_rate
+                .checked_sub(last_tick.elapsed())
+                .unwrap_or_else(|| Duration::from_secs(0));
+
+            if event::poll(timeout).expect("Poll works") {
+                match event::read().expect("Can read events") {
+                    CEvent::Key(key) => tx.send(Event::Input(key)).expect("Can send events"),
+                    CEvent::Mouse(mouse) => tx.send(Event::Mouse(mouse)).expect("Can send events"),
+                    CEvent::Resize(_, _) => tx.send(Event::Resize).expect("Can send events"),
+                    _ => {}
+                }
+            }
+
+            if last_tick.elapsed() >= tick_rate && tx.send(Event::Tick).is_ok() {
+                last_tick = Instant::now();
+            }
+        }
+    });
+
+    let stdout = io::stdout();
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    terminal.clear()?;
+
+    let mut show_pop_up = false;
+    let mut input_mode = InputMode::Normal;
+    let mut active_menu_item = MenuItem::Home;
+    let mut previous_menu_item = MenuItem::Home;
+    let mut split_view: Option<SplitView> = None;
+    let mut archive = read_archive(db_path)?;
+    let mut archive_state = ListState::default();
+    let mut tag_state = ListState::default();
+    archive_state.select(Some(0));
+    let mut task_list_state = ListState::default();
+    let mut corrupt_db_error = String::new();
+    // A read-only session must not rewrite an older file in the new format.
+    let loaded = if args.read_only {
+        fs::read_to_string(db_path)
+            .map_err(Error::from)
+            .and_then(|content| Ok(parse_db(&content, format)?.0))
+    } else {
+        read_db(db_path, format)
+    };
+    let mut db = match loaded {
+        Ok(db) => db,
+        Err(Error::ParseDBError(e)) => {
+            corrupt_db_error = e.to_string();
+            input_mode = InputMode::RecoverDb;
+            Database {
+                format,
+                ..Database::default()
+            }
+        }
+        Err(e) => return Err(e),
+    };
+    db.debounce = true;
+    let selected = read_state(db_path)
+        .selected
+        .min(db.tasks.len().saturating_sub(1));
+    task_list_state.select(Some(selected));
+    // Tasks overdue at startup are covered by the reminders popup.
+    let mut notify = args.notify;
+    let mut clipboard: Option<arboard::Clipboard> = None;
+    let mut notified: HashSet<usize> = HashSet::new();
+    newly_overdue(&db.tasks, &mut notified, Utc::now());
+    let mut reminders: Vec<String> = upcoming_tasks(&db.tasks, chrono::Duration::hours(24))
+        .iter()
+        .map(|task| {
+            let due_at = task.due_at.expect("Upcoming tasks have a due date.");
+            let suffix = if is_overdue(task, Utc::now()) {
+                " (overdue)"
+            } else {
+                ""
+            };
+            format!("{} - due {}{}", task.name, to_local_date(&due_at), suffix)
+        })
+        .collect();
+    if !reminders.is_empty() && matches!(input_mode, InputMode::Normal) {
+        input_mode = InputMode::Reminders;
+    }
+    // Reminders fire once per due date, so snoozing a task sets it again.
+    let mut reminded: HashSet<(usize, DateTime<Utc>)> = HashSet::new();
+    for task in upcoming_tasks(&db.tasks, chrono::Duration::hours(24)) {
+        if let Some(due_at) = task.due_at {
+            reminded.insert((task.id, due_at));
+        }
+    }
+    if first_run && !config.tutorial_seen && !args.read_only {
+        input_mode = InputMode::Tutorial;
+    }
+    let mut new_task = String::new();
+    let mut new_task_due = String::new();
+    let mut new_task_estimate = String::new();
+    let mut timer: Option<(usize, Instant)> = None;
+    let mut notes = String::new();
+    let mut active_field = PopupField::Name;
+    let mut editing_task_id: Option<usize> = None;
+    let mut new_task_parent: Option<usize> = None;
+    // One entry per delete, holding every task it removed.
+    let mut deleted_tasks: Vec<Vec<(usize, Task)>> = vec![];
+    let mut delete_candidate = String::new();
+    let mut date_range_input = String::new();
+    let mut target_task_id = None;
+    let mut reminder_input = String::new();
+    let mut start_input = String::new();
+    let mut marked: HashSet<usize> = HashSet::new();
+    let mut list_area = Rect::default();
+    let mut list_offset = 0;
+    let mut list_pages = ListPages::default();
+    // The task being dragged with the mouse, by id.
+    let mut dragged: Option<usize> = None;
+    let mut error_message: Option<String> = None;
+    // Input the add popup could not use, shown inside it.
+    let mut popup_error: Option<String> = None;
+    let mut info_message = (db.tasks.len() > LARGE_DB_WARNING).then(|| {
+        format!(
+            "{} tasks loaded; archive completed ones to keep the list responsive",
+            db.tasks.len()
+        )
+    });
+    let mut log: VecDeque<LogEntry> = VecDeque::new();
+    let mut log_scroll: u16 = 0;
+    let mut help_scroll: u16 = 0;
+    // Dropping the watcher stops it, so it lives as long as the loop.
+    let _watcher = match watch_db(db_path, watch_tx) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            push_log(
+                &mut log,
+                &format!("Not watching the task file: {}", e),
+                true,
+            );
+            None
+        }
+    };
+    let mut view_options = ViewOptions {
+        task_filter: config.default_filter,
+        dense: config.dense,
+        sink_completed: config.sink_completed,
+        ..ViewOptions::default()
+    };
+
+    let mut needs_redraw = true;
+    let mut drawn_minute = 0;
+    loop {
+        if needs_redraw {
+            terminal.draw(|rect| {
+                let size = rect.size();
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(2)
+                    .constraints(
+                        [
+                            Constraint::Length(3),
+                            Constraint::Min(2),
+                            Constraint::Length(3),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(size);
+
+                let completed = db
+                    .tasks
+                    .iter()
+                    .filter(|task| task.completed_at.is_some())
+                    .count();
+                let menu_titles = [
+                    String::from("Home"),
+                    format!("Tasks ({}/{})", completed, db.tasks.len()),
+                    String::from("Stats"),
+                    String::from("Archive"),
+                ];
+                let menu = menu_titles
+                    .iter()
+                    .map(|t| {
+                        let (first, rest) = t.split_at(1);
+                        Spans::from(vec![
+                            Span::styled(
+                                first,
+                                Style::default()
+                                    .fg(theme.accent)
+                                    .add_modifier(Modifier::UNDERLINED),
+                            ),
+                            Span::styled(rest, Style::default().fg(theme.fg)),
+                        ])
+                    })
+                    .collect();
+
+                let tabs = Tabs::new(menu)
+                    .select(active_menu_item.into())
+                    .block(Block::default().title("Menu").borders(Borders::ALL))
+                    .style(Style::default().fg(theme.fg))
+                    .highlight_style(Style::default().fg(theme.accent))
+                    .divider(Span::raw("|"));
+
+                rect.render_widget(tabs, chunks[0]);
+
+                match active_menu_item {
+                    MenuItem::Home => {
+                        let mut constraints = vec![Constraint::Min(9), Constraint::Length(3)];
+                        if config.daily_goal.is_some() {
+                            constraints.push(Constraint::Length(3));
+                        }
+                        let home_chunks = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints(constraints)
+                            .split(chunks[1]);
+                        let (welcome, progress) = render_home(&db.tasks, &theme);
+                        rect.render_widget(welcome, home_chunks[0]);
+                        rect.render_widget(progress, home_chunks[1]);
+                        if let Some(goal) = config.daily_goal {
+                            let today = Local::now().date_naive();
+                            rect.render_widget(
+                                render_daily_goal(&db.tasks, goal, today, &theme),
+                                home_chunks[2],
+                            );
+                        }
+                    }
+                    MenuItem::Tasks => {
+                        let list_width = if split_view.is_some() { 50 } else { 20 };
+                        let todo_chunks = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints(
+                                [
+                                    Constraint::Percentage(list_width),
+                                    Constraint::Percentage(100 - list_width),
+                                ]
+                                .as_ref(),
+                            )
+                            .split(chunks[1]);
+                        let detail_chunks = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints(
+                                [
+                                    // Header and row, plus borders unless dense.
+                                    Constraint::Length(if view_options.dense { 2 } else { 4 }),
+                                    Constraint::Min(3),
+                                ]
+                                .as_ref(),
+                            )
+                            .split(todo_chunks[1]);
+                        list_area = todo_chunks[0];
+                        if let Some(split) = &mut split_view {
+                            let columns = Layout::default()
+                                .direction(Direction::Horizontal)
+                                .constraints(
+                                    [Constraint::Percentage(50), Constraint::Percentage(50)]
+                                        .as_ref(),
+                                )
+                                .split(list_area);
+                            let other_options = ViewOptions {
+                                task_filter: other_column(view_options.task_filter),
+                                ..view_options.clone()
+                            };
+                            let (other_area, focused_area) =
+                                if view_options.task_filter == TaskFilter::Active {
+                                    (columns[1], columns[0])
+                                } else {
+                                    (columns[0], columns[1])
+                                };
+                            let (other, _, _, _) = render_todo(
+                                &db.tasks,
+                                &mut split.other_state,
+                                &other_options,
+                                &marked,
+                                None,
+                                other_area,
+                                &theme,
+                            );
+                            // Only the focused column shows its selection.
+                            rect.render_stateful_widget(
+                                other,
+                                other_area,
+                                &mut ListState::default(),
+                            );
+                            list_area = focused_area;
+                        }
+                        let (left, right, task_notes, pages) = render_todo(
+                            &db.tasks,
+                            &mut task_list_state,
+                            &view_options,
+                            &marked,
+                            dragged,
+                            list_area,
+                            &theme,
+                        );
+                        // Only the current page is handed to the list, so its
+                        // selection is relative to the first task on the page.
+                        let selected = task_list_state.selected();
+                        list_offset = pages.start_of(selected.unwrap_or(0));
+                        list_pages = pages;
+                        let mut page_state = ListState::default();
+                        page_state.select(selected.map(|selected| selected - list_offset));
+                        rect.render_stateful_widget(left, list_area, &mut page_state);
+                        rect.render_widget(right, detail_chunks[0]);
+                        rect.render_widget(task_notes, detail_chunks[1]);
+                    }
+                    MenuItem::Stats => {
+                        let stats_chunks = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints(
+                                [Constraint::Percentage(60), Constraint::Percentage(40)].as_ref(),
+                            )
+                            .split(chunks[1]);
+                        let left_chunks = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Min(3), Constraint::Length(6)].as_ref())
+                            .split(stats_chunks[0]);
+                        rect.render_widget(
+                            render_stats(&db.tasks, config.daily_goal, &theme),
+                            left_chunks[0],
+                        );
+                        rect.render_widget(
+                            render_weekly_summary(&weekly_summary(&db.tasks, Utc::now()), &theme),
+                            left_chunks[1],
+                        );
+                        let counts = tag_counts(&db.tasks);
+                        clamp_selection(&mut tag_state, counts.len());
+                        rect.render_stateful_widget(
+                            render_tag_counts(&counts, &theme),
+                            stats_chunks[1],
+                            &mut tag_state,
+                        );
+                    }
+                    MenuItem::Archive => {
+                        clamp_selection(&mut archive_state, archive.len());
+                        rect.render_stateful_widget(
+                            render_archive(&archive, &theme),
+                            chunks[1],
+                            &mut archive_state,
+                        );
+                    }
+                }
+
+                let (footer_text, footer_title, footer_color) =
+                    match (&error_message, &info_message) {
+                        (Some(message), _) => (message.clone(), String::from("Error"), Color::Red),
+                        (None, Some(message)) => {
+                            (message.clone(), String::from("Status"), Color::Green)
+                        }
+                        (None, None) if args.read_only => (
+                            format!(
+                                "{} | READ-ONLY",
+                                status_line(&db.tasks, &view_options, &input_mode)
+                            ),
+                            footer_text(),
+                            theme.info,
+                        ),
+                        (None, None) => (
+                            status_line(&db.tasks, &view_options, &input_mode),
+                            footer_text(),
+                            theme.info,
+                        ),
+                    };
+                // The asterisk stays up until pending edits reach the disk.
+                let footer_title = match db.modified_at {
+                    Some(_) => format!("{} *", footer_title),
+                    None => footer_title,
+                };
+                let footer = Paragraph::new(footer_text)
+                    .style(Style::default().fg(footer_color))
+                    .alignment(Alignment::Center)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .style(Style::default().fg(theme.fg))
+                            .title(footer_title)
+                            .border_type(BorderType::Plain),
+                    );
+
+                rect.render_widget(footer, chunks[2]);
+
+                if let InputMode::RecoverDb = input_mode {
+                    let (block, area) = render_popup("Corrupt database", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let prompt = Paragraph::new(vec![
+                        Spans::from(format!("Could not parse {}: {}", db_path, corrupt_db_error)),
+                        Spans::from(""),
+                        Spans::from(format!(
+                            "Back it up to {}.corrupt and start with an empty list? (y/n)",
+                            db_path
+                        )),
+                    ])
+                    .style(Style::default().fg(Color::Red))
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .block(block);
+                    rect.render_widget(prompt, area);
+                }
+
+                if let InputMode::Reminders = input_mode {
+                    let (block, area) =
+                        render_popup("Upcoming/overdue tasks (Esc to dismiss)", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let lines: Vec<Spans> = reminders
+                        .iter()
+                        .map(|reminder| Spans::from(reminder.as_str()))
+                        .collect();
+                    let list = Paragraph::new(lines)
+                        .style(Style::default().fg(theme.accent))
+                        .block(block);
+                    rect.render_widget(list, area);
+                }
+
+                if let InputMode::Log = input_mode {
+                    let lines: Vec<Spans> = log
+                        .iter()
+                        .rev()
+                        .map(|entry| {
+                            let color = if entry.is_error { Color::Red } else { theme.fg };
+                            Spans::from(vec![
+                                Span::styled(
+                                    format!("{} ", entry.at.format("%H:%M:%S")),
+                                    Style::default().fg(theme.info),
+                                ),
+                                Span::styled(entry.message.clone(), Style::default().fg(color)),
+                            ])
+                        })
+                        .collect();
+                    let log_panel = Paragraph::new(lines).scroll((log_scroll, 0)).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .style(Style::default().fg(theme.fg))
+                            .title("Log (newest first)")
+                            .border_type(BorderType::Plain),
+                    );
+                    rect.render_widget(Clear, size);
+                    rect.render_widget(log_panel, size);
+                }
+
+                if let InputMode::Focus = input_mode {
+                    let task = selected_task(&db.tasks, &task_list_state, &view_options);
+                    rect.render_widget(Clear, size);
+                    rect.render_widget(render_focus(task, size.height, &theme), size);
+                }
+
+                if let InputMode::Tutorial = input_mode {
+                    let (area, lines) = fit_lines(tutorial_lines(&key_bindings, &theme), size);
+                    rect.render_widget(Clear, area);
+                    rect.render_widget(render_tutorial(lines, &theme), area);
+                }
+
+                if let InputMode::Help = input_mode {
+                    let lines = help_lines(&key_bindings, &theme);
+                    help_scroll = help_scroll.min(help_max_scroll(lines.len(), size));
+                    rect.render_widget(Clear, size);
+                    rect.render_widget(render_help(lines, help_scroll, &theme), size);
+                }
+
+                if let InputMode::ConfirmDelete = input_mode {
+                    let (block, area) = render_popup("Confirm", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let confirm = Paragraph::new(format!("Delete {}? (y/n)", delete_candidate))
+                        .style(Style::default().fg(Color::Red))
+                        .alignment(Alignment::Center)
+                        .block(block);
+                    rect.render_widget(confirm, area);
+                }
+
+                if let InputMode::ConfirmClear = input_mode {
+                    let completed = db
+                        .tasks
+                        .iter()
+                        .filter(|task| task.completed_at.is_some())
+                        .count();
+                    let (block, area) = render_popup("Confirm", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let confirm = Paragraph::new(format!(
+                        "Archive {} completed task{}? (y/n)",
+                        completed,
+                        if completed == 1 { "" } else { "s" }
+                    ))
+                    .style(Style::default().fg(Color::Red))
+                    .alignment(Alignment::Center)
+                    .block(block);
+                    rect.render_widget(confirm, area);
+                }
+
+                if let InputMode::Notes = input_mode {
+                    let (block, area) = render_popup("Notes (Ctrl+S to save)", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let inner = block.inner(area);
+                    let editor = Paragraph::new(notes.as_str())
+                        .style(Style::default().fg(theme.input))
+                        .block(block);
+                    rect.render_widget(editor, area);
+                    let lines: Vec<&str> = notes.split('\n').collect();
+                    let last_line = lines.last().map_or(0, |line| line.width());
+                    rect.set_cursor(
+                        inner.x + last_line as u16,
+                        inner.y + (lines.len() as u16 - 1).min(inner.height.saturating_sub(1)),
+                    );
+                }
+
+                if let InputMode::DateRange = input_mode {
+                    let (block, area) =
+                        render_popup("Created (YYYY-MM-DD..YYYY-MM-DD)", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let inner = block.inner(area);
+                    let input = Paragraph::new(date_range_input.as_ref())
+                        .style(Style::default().fg(theme.input))
+                        .block(block);
+                    rect.render_widget(input, area);
+                    rect.set_cursor(inner.x + date_range_input.width() as u16, inner.y);
+                }
+
+                if let InputMode::ReminderLead = input_mode {
+                    let (block, area) =
+                        render_popup("Remind how many hours before due?", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let inner = block.inner(area);
+                    let input = Paragraph::new(reminder_input.as_ref())
+                        .style(Style::default().fg(theme.input))
+                        .block(block);
+                    rect.render_widget(input, area);
+                    rect.set_cursor(inner.x + reminder_input.width() as u16, inner.y);
+                }
+
+                if let InputMode::StartDate = input_mode {
+                    let (block, area) =
+                        render_popup("Hide until (YYYY-MM-DD, tomorrow, +3d)", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let inner = block.inner(area);
+                    let input = Paragraph::new(start_input.as_ref())
+                        .style(Style::default().fg(theme.input))
+                        .block(block);
+                    rect.render_widget(input, area);
+                    rect.set_cursor(inner.x + start_input.width() as u16, inner.y);
+                }
+
+                if let InputMode::Snooze = input_mode {
+                    let (block, area) = render_popup("Snooze by", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let choices: Vec<Spans> = SNOOZE_CHOICES
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (label, _))| {
+                            Spans::from(vec![
+                                Span::styled(
+                                    format!("{}  ", i + 1),
+                                    Style::default()
+                                        .fg(theme.accent)
+                                        .add_modifier(Modifier::BOLD),
+                                ),
+                                Span::raw(*label),
+                            ])
+                        })
+                        .collect();
+                    rect.render_widget(Paragraph::new(choices).block(block), area);
+                }
+
+                if let InputMode::Searching = input_mode {
+                    let (block, area) = render_popup("Search", size, &theme);
+                    rect.render_widget(Clear, area);
+                    let inner = block.inner(area);
+                    let input = Paragraph::new(view_options.filter.as_ref())
+                        .style(Style::default().fg(theme.input))
+                        .block(block);
+                    rect.render_widget(input, area);
+                    rect.set_cursor(inner.x + view_options.filter.width() as u16, inner.y);
+                }
+
+                if show_pop_up {
+                    let title = match (editing_task_id, new_task_parent) {
+                        (Some(_), _) => "Edit task",
+                        (None, Some(_)) => "Add subtask",
+                        (None, None) => "Add task",
+                    };
+                    let (block, area) = render_popup(title, size, &theme);
+                    let mut fields = vec![(PopupField::Name, "Name: ", &new_task)];
+                    if editing_task_id.is_none() {
+                        fields.push((
+                            PopupField::Due,
+                            "Due (YYYY-MM-DD, tomorrow, +3d): ",
+                            &new_task_due,
+                        ));
+                    }
+                    fields.push((
+                        PopupField::Estimate,
+                        "Estimate (minutes): ",
+                        &new_task_estimate,
+                    ));
+                    let rows = fields.len() as u16;
+                    // The popup grows to fit the fields and any error below them.
+                    let error_lines = popup_error
+                        .as_ref()
+                        .map(|message| {
+                            wrap_spans(
+                                &[Span::styled(
+                                    message.clone(),
+                                    Style::default().fg(theme.error),
+                                )],
+                                area.width.saturating_sub(2) as usize,
+                                0,
+                            )
+                        })
+                        .unwrap_or_default();
+                    let area = grow_rect(area, rows + error_lines.len() as u16 + 2, size);
+                    rect.render_widget(Clear, area);
+                    let inner = block.inner(area);
+                    rect.render_widget(block, area);
+                    for (row, (field, label, value)) in fields.into_iter().enumerate() {
+                        let row = row as u16;
+                        if row >= inner.height {
+                            break;
+                        }
+                        let line = Rect::new(inner.x, inner.y + row, inner.width, 1);
+                        let input = Paragraph::new(Spans::from(vec![
+                            Span::styled(label, Style::default().fg(theme.fg)),
+                            Span::styled(value.as_str(), Style::default().fg(theme.input)),
+                        ]));
+                        rect.render_widget(input, line);
+
+                        if let InputMode::Editing = input_mode {
+                            if field == active_field {
+                                rect.set_cursor(
+                                    line.x + (label.width() + value.width()) as u16,
+                                    line.y,
+                                );
+                            }
+                        }
+                    }
+                    if rows < inner.height {
+                        let below =
+                            Rect::new(inner.x, inner.y + rows, inner.width, inner.height - rows);
+                        rect.render_widget(Paragraph::new(error_lines), below);
+                    }
+                }
+            })?;
+            drawn_minute = Utc::now().timestamp() / 60;
+        }
+
+        // Ticks only keep the loop alive, apart from redrawing once a minute
+        // so countdowns stay current; everything else may change what is on
+        // screen.
+        let event = rx.recv()?;
+        needs_redraw = match event {
+            Event::Tick => Utc::now().timestamp() / 60 != drawn_minute,
+            _ => true,
+        };
+        match event {
+            Event::Input(event) => {
+                error_message = None;
+                popup_error = None;
+                info_message = None;
+                match input_mode {
+                    InputMode::Normal => match key_bindings.action(event.code) {
+                        // Alt+letter jumps to the next task starting with it
+                        // instead of running the letter's action.
+                        _ if event.modifiers.contains(KeyModifiers::ALT) => {
+                            if let KeyCode::Char(c) = event.code {
+                                let visible: Vec<Task> = view_indices(&db.tasks, &view_options)
+                                    .into_iter()
+                                    .map(|index| db.tasks[index].clone())
+                                    .collect();
+                                let from = task_list_state.selected().unwrap_or(0);
+                                if let Some(index) = next_matching_index(&visible, from, c) {
+                                    task_list_state.select(Some(index));
+                                }
+                            }
+                        }
+                        // On the Stats tab, Enter filters the task list by the
+                        // selected tag.
+                        _ if event.code == KeyCode::Enter
+                            && active_menu_item == MenuItem::Stats =>
+                        {
+                            let counts = tag_counts(&db.tasks);
+                            if let Some((tag, _)) = tag_state
+                                .selected()
+                                .and_then(|selected| counts.get(selected))
+                            {
+                                view_options.tag = Some(tag.clone());
+                                task_list_state.select(Some(0));
+                                active_menu_item = MenuItem::Tasks;
+                            }
+                        }
+                        Some(action) if !action.applies_in(active_menu_item) => {}
+                        Some(action) if args.read_only && action.mutates() => {
+                            error_message = Some(Error::ReadOnly.to_string())
+                        }
+                        Some(Action::Quit) => break,
+                        Some(
+                            action @ (Action::ShowHome
+                            | Action::ShowTasks
+                            | Action::ShowStats
+                            | Action::ShowArchive),
+                        ) => {
+                            let menu_item = match action {
+                                Action::ShowHome => MenuItem::Home,
+                                Action::ShowTasks => MenuItem::Tasks,
+                                Action::ShowStats => MenuItem::Stats,
+                                _ => MenuItem::Archive,
+                            };
+                            // Re-selecting the current tab keeps the one to
+                            // go back to.
+                            if menu_item != active_menu_item {
+                                previous_menu_item = active_menu_item;
+                                active_menu_item = menu_item;
+                            }
+                        }
+                        Some(Action::PreviousTab) => match &mut split_view {
+                            Some(split) if active_menu_item == MenuItem::Tasks => {
+                                std::mem::swap(&mut task_list_state, &mut split.other_state);
+                                view_options.task_filter = other_column(view_options.task_filter);
+                            }
+                            _ => std::mem::swap(&mut active_menu_item, &mut previous_menu_item),
+                        },
+                        Some(Action::ToggleSplit) => {
+                            let selected =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                                    .map(|task| task.id);
+                            match split_view.take() {
+                                Some(split) => view_options.task_filter = split.filter_before,
+                                None => {
+                                    split_view = Some(SplitView {
+                                        filter_before: view_options.task_filter,
+                                        other_state: ListState::default(),
+                                    });
+                                    view_options.task_filter = TaskFilter::Active;
+                                    task_list_state.select(Some(0));
+                                }
+                            }
+                            if let Some(id) = selected {
+                                select_task_by_id(
+                                    &db.tasks,
+                                    &mut task_list_state,
+                                    &view_options,
+                                    id,
+                                );
+                            }
+                        }
+                        Some(Action::CycleFilter | Action::TodayFilter) if split_view.is_some() => {
+                            info_message =
+                                Some(String::from("Close the split view to change the filter"))
+                        }
+                        Some(Action::ArchiveTask) => {
+                            match selected_task(&db.tasks, &task_list_state, &view_options) {
+                                Some(task) if task.completed_at.is_none() => {
+                                    error_message =
+                                        Some(String::from("Only completed tasks can be archived"));
+                                }
+                                Some(_) => {
+                                    match archive_task(
+                                        db_path,
+                                        &mut db,
+                                        &mut archive,
+                                        &mut task_list_state,
+                                        &view_options,
+                                    ) {
+                                        Ok(()) => {
+                                            info_message = Some(String::from("Task archived"))
+                                        }
+                                        Err(e) => error_message = Some(e.to_string()),
+                                    }
+                                }
+                                None => {}
+                            }
+                        }
+                        Some(Action::RestoreTask)
+                            if matches!(active_menu_item, MenuItem::Archive) =>
+                        {
+                            match unarchive_task(db_path, &mut db, &mut archive, &archive_state) {
+                                Ok(()) => info_message = Some(String::from("Task restored")),
+                                Err(e) => error_message = Some(e.to_string()),
+                            }
+                        }
+                        Some(Action::AddTask) => {
+                            show_pop_up = true;
+                            input_mode = InputMode::Editing;
+                        }
+                        Some(Action::AddSubtask) => {
+                            if let Some(task) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                            {
+                                // Subtasks only nest one level deep, so adding one
+                                // under a subtask makes a sibling instead.
+                                new_task_parent = Some(task.parent_id.unwrap_or(task.id));
+                                show_pop_up = true;
+                                input_mode = InputMode::Editing;
+                            }
+                        }
+                        Some(Action::EditTask) => {
+                            if let Some(task) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                            {
+                                new_task = task.name.clone();
+                                new_task_estimate = task
+                                    .estimate_minutes
+                                    .map(|minutes| minutes.to_string())
+                                    .unwrap_or_default();
+                                editing_task_id = Some(task.id);
+                                show_pop_up = true;
+                                input_mode = InputMode::Editing;
+                            }
+                        }
+                        Some(Action::EditNotes) => {
+                            if let Some(task) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                            {
+                                notes = task.notes.clone();
+                                editing_task_id = Some(task.id);
+                                input_mode = InputMode::Notes;
+                            }
+                        }
+                        Some(Action::DuplicateTask) => {
+                            if let Some(id) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                                    .map(|task| task.id)
+                            {
+                                match duplicate_task(db_path, &mut db, id) {
+                                    Ok(new_id) => {
+                                        select_task_by_id(
+                                            &db.tasks,
+                                            &mut task_list_state,
+                                            &view_options,
+                                            new_id,
+                                        );
+                                        info_message = Some(String::from("Task duplicated"));
+                                    }
+                                    Err(e) => error_message = Some(e.to_string()),
+                                }
+                            }
+                        }
+                        Some(Action::Mark) => {
+                            if let Some(task) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                            {
+                                if !marked.remove(&task.id) {
+                                    marked.insert(task.id);
+                                }
+                            }
+                        }
+                        Some(Action::DeleteTask) => {
+                            if !marked.is_empty() {
+                                delete_candidate = format!("{} selected tasks", marked.len());
+                                input_mode = InputMode::ConfirmDelete;
+                            } else if let Some(task) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                            {
+                                let subtasks = db
+                                    .tasks
+                                    .iter()
+                                    .filter(|subtask| subtask.parent_id == Some(task.id))
+                                    .count();
+                                delete_candidate = match subtasks {
+                                    0 => format!("'{}'", task.name),
+                                    1 => format!("'{}' and its subtask", task.name),
+                                    _ => format!("'{}' and its {} subtasks", task.name, subtasks),
+                                };
+                                input_mode = InputMode::ConfirmDelete;
+                            }
+                        }
+                        Some(Action::Undo) => {
+                            if let Some(removed) = deleted_tasks.pop() {
+                                let message = match removed.as_slice() {
+                                    [(_, task)] => format!("Restored '{}'", task.name),
+                                    removed => format!("Restored {} tasks", removed.len()),
+                                };
+                                let id = removed.first().map(|(_, task)| task.id);
+                                match restore_tasks(db_path, &mut db, removed) {
+                                    Ok(()) => info_message = Some(message),
+                                    Err(e) => error_message = Some(e.to_string()),
+                                }
+                                if let Some(id) = id {
+                                    select_task_by_id(
+                                        &db.tasks,
+                                        &mut task_list_state,
+                                        &view_options,
+                                        id,
+                                    );
+                                }
+                            }
+                        }
+                        Some(Action::Complete) => {
+                            let result = if marked.is_empty() {
+                                complete_task(db_path, &mut db, &task_list_state, &view_options)
+                            } else {
+                                complete_tasks(db_path, &mut db, &marked)
+                            };
+                            marked.clear();
+                            match result {
+                                Ok(()) => info_message = Some(String::from("Completion updated")),
+                                Err(e) => error_message = Some(e.to_string()),
+                            }
+                        }
+                        Some(Action::ClearCompleted) => {
+                            if db.tasks.iter().any(|task| task.completed_at.is_some()) {
+                                input_mode = InputMode::ConfirmClear;
+                            } else {
+                                info_message = Some(String::from("No completed tasks to clear"));
+                            }
+                        }
+                        Some(Action::ToggleTimer) => {
+                            let selected =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                                    .map(|task| task.id);
+                            if let Some((id, started)) = timer.take() {
+                                match log_time(db_path, &mut db, id, started) {
+                                    Ok(minutes) => {
+                                        info_message =
+                                            Some(format!("Logged {}", fmt_minutes(minutes)))
+                                    }
+                                    Err(e) => error_message = Some(e.to_string()),
+                                }
+                                // Pressing it on another task moves the timer there.
+                                if selected != Some(id) {
+                                    timer = selected.map(|id| (id, Instant::now()));
+                                }
+                            } else if let Some(id) = selected {
+                                timer = Some((id, Instant::now()));
+                                info_message = Some(String::from("Timer started"));
+                            }
+                        }
+                        Some(Action::CycleSort) => {
+                            view_options.sort_mode = view_options.sort_mode.next()
+                        }
+                        Some(Action::ToggleAgeColors) => {
+                            view_options.age_colors = !view_options.age_colors
+                        }
+                        Some(Action::ToggleDense) => {
+                            view_options.dense = !view_options.dense;
+                            if let Err(e) =
+                                set_config_value("dense", &view_options.dense.to_string())
+                            {
+                                error_message = Some(e.to_string());
+                            }
+                        }
+                        Some(Action::ToggleAbsoluteTimes) => {
+                            view_options.absolute_times = !view_options.absolute_times
+                        }
+                        Some(Action::CycleFilter) => {
+                            view_options.task_filter = view_options.task_filter.next();
+                            task_list_state.select(Some(0));
+                        }
+                        Some(Action::TodayFilter) => {
+                            view_options.task_filter = match view_options.task_filter {
+                                TaskFilter::Today => TaskFilter::All,
+                                _ => TaskFilter::Today,
+                            };
+                            task_list_state.select(Some(0));
+                        }
+                        Some(Action::CycleTag) => {
+                            view_options.tag = next_tag(&db.tasks, view_options.tag.as_deref());
+                            task_list_state.select(Some(0));
+                        }
+                        Some(Action::Help) => {
+                            help_scroll = 0;
+                            input_mode = InputMode::Help;
+                        }
+                        Some(Action::Focus) => input_mode = InputMode::Focus,
+                        Some(Action::CopyName) => {
+                            if let Some(task) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                            {
+                                match copy_to_clipboard(&mut clipboard, &task.name) {
+                                    Ok(()) => info_message = Some(String::from("Copied")),
+                                    Err(e) => error_message = Some(e.to_string()),
+                                }
+                            }
+                        }
+                        Some(Action::Save) => match flush_db(db_path, &mut db) {
+                            Ok(()) => info_message = Some(String::from("Saved")),
+                            Err(e) => error_message = Some(e.to_string()),
+                        },
+                        Some(Action::Reload) => match reload_db(db_path, &mut db) {
+                            Ok(()) => {
+                                marked.clear();
+                                clamp_selection(
+                                    &mut task_list_state,
+                                    view_indices(&db.tasks, &view_options).len(),
+                                );
+                                info_message = Some(String::from("Reloaded"));
+                            }
+                            Err(e) => error_message = Some(e.to_string()),
+                        },
+                        Some(Action::EditExternally) => {
+                            input_paused.store(true, Ordering::SeqCst);
+                            // Lets a poll already under way time out first.
+                            thread::sleep(tick_rate);
+                            restore_terminal();
+                            let edited = edit_db_externally(db_path, &mut db);
+                            enable_raw_mode()?;
+                            execute!(io::stdout(), EnableMouseCapture)?;
+                            terminal.clear()?;
+                            input_paused.store(false, Ordering::SeqCst);
+                            match edited {
+                                Ok(()) => {
+                                    marked.clear();
+                                    clamp_selection(
+                                        &mut task_list_state,
+                                        view_indices(&db.tasks, &view_options).len(),
+                                    );
+                                    info_message = Some(String::from("Reloaded"));
+                                }
+                                Err(e) => error_message = Some(e.to_string()),
+                            }
+                        }
+                        Some(Action::ShowLog) => {
+                            log_scroll = 0;
+                            input_mode = InputMode::Log;
+                        }
+                        Some(Action::Search) => input_mode = InputMode::Searching,
+                        Some(Action::DateRange) => {
+                            date_range_input = view_options
+                                .created
+                                .as_ref()
+                                .map(DateRange::label)
+                                .unwrap_or_default();
+                            input_mode = InputMode::DateRange;
+                        }
+                        Some(Action::ClearSearch) => {
+                            view_options.filter.clear();
+                            task_list_state.select(Some(0));
+                        }
+                        Some(Action::CycleRecurrence) => {
+                            if let Err(e) = cycle_task_recurrence(
+                                db_path,
+                                &mut db,
+                                &task_list_state,
+                                &view_options,
+                            ) {
+                                error_message = Some(e.to_string());
+                            }
+                        }
+                        Some(Action::Snooze) => {
+                            if let Some(task) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                            {
+                                target_task_id = Some(task.id);
+                                input_mode = InputMode::Snooze;
+                            }
+                        }
+                        Some(Action::SetReminder) => {
+                            match selected_task(&db.tasks, &task_list_state, &view_options) {
+                                Some(task) if task.due_at.is_none() => {
+                                    error_message = Some(Error::NoDueDate.to_string())
+                                }
+                                Some(task) => {
+                                    reminder_input = task
+                                        .reminder_lead
+                                        .map(|lead| lead.num_hours().to_string())
+                                        .unwrap_or_default();
+                                    target_task_id = Some(task.id);
+                                    input_mode = InputMode::ReminderLead;
+                                }
+                                None => {}
+                            }
+                        }
+                        Some(Action::Defer) => {
+                            if let Some(task) =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                            {
+                                start_input = task
+                                    .start_at
+                                    .map(|start_at| to_local_date(&start_at))
+                                    .unwrap_or_default();
+                                target_task_id = Some(task.id);
+                                input_mode = InputMode::StartDate;
+                            }
+                        }
+                        Some(Action::ToggleSinkCompleted) => {
+                            // The same task stays selected where it moves to.
+                            let selected =
+                                selected_task(&db.tasks, &task_list_state, &view_options)
+                                    .map(|task| task.id);
+                            view_options.sink_completed = !view_options.sink_completed;
+                            if let Some(id) = selected {
+                                select_task_by_id(
+                                    &db.tasks,
+                                    &mut task_list_state,
+                                    &view_options,
+                                    id,
+                                );
+                            }
+                        }
+                        Some(Action::ToggleDeferred) => {
+                            view_options.show_deferred = !view_options.show_deferred;
+                            let amount_tasks = view_indices(&db.tasks, &view_options).len();
+                            clamp_selection(&mut task_list_state, amount_tasks);
+                        }
+                        Some(Action::CycleFlag) => {
+                            if let Err(e) =
+                                cycle_task_flag(db_path, &mut db, &task_list_state, &view_options)
+                            {
+                                error_message = Some(e.to_string());
+                            }
+                        }
+                        Some(Action::CyclePriority) => {
+                            if let Err(e) = cycle_task_priority(
+                                db_path,
+                                &mut db,
+                                &task_list_state,
+                                &view_options,
+                            ) {
+                                error_message = Some(e.to_string());
+                            }
+                        }
+                        Some(action @ (Action::MoveTaskUp | Action::MoveTaskDown)) => {
+                            if view_options.sort_mode != SortMode::None {
+                                error_message =
+                                    Some(String::from("Clear the sort order to reorder tasks"));
+                            } else {
+                                let direction = if action == Action::MoveTaskDown {
+                                    1
+                                } else {
+                                    -1
+                                };
+                                if let Err(e) = move_selected_task(
+                                    db_path,
+                                    &mut db,
+                                    &mut task_list_state,
+                                    &view_options,
+                                    direction,
+                                ) {
+                                    error_message = Some(e.to_string());
+                                }
+                            }
+                        }
+                        Some(Action::SelectFirst) => {
+                            let amount_tasks = view_indices(&db.tasks, &view_options).len();
+                            jump_selection(&mut task_list_state, amount_tasks, false);
+                        }
+                        Some(Action::SelectLast) => {
+                            let amount_tasks = view_indices(&db.tasks, &view_options).len();
+                            jump_selection(&mut task_list_state, amount_tasks, true);
+                        }
+                        Some(action @ (Action::PageUp | Action::PageDown)) => {
+                            // Pages hold however many wrapped tasks fit, so
+                            // jump to the first task of the neighbouring page.
+                            let amount_tasks = view_indices(&db.tasks, &view_options).len();
+                            let selected = task_list_state.selected().unwrap_or(0);
+                            let page = list_pages.page_of(selected);
+                            let next = if action == Action::PageDown {
+                                list_pages.starts.get(page + 1).copied()
+                            } else {
+                                page.checked_sub(1).map(|page| list_pages.starts[page])
+                            };
+                            let last = amount_tasks.saturating_sub(1);
+                            let next =
+                                next.unwrap_or(if action == Action::PageDown { last } else { 0 });
+                            if amount_tasks > 0 {
+                                task_list_state.select(Some(next.min(last)));
+                            }
+                        }
+                        Some(action @ (Action::SelectUp | Action::SelectDown)) => {
+                            let delta = if action == Action::SelectDown { 1 } else { -1 };
+                            if let MenuItem::Archive = active_menu_item {
+                                move_selection(
+                                    &mut archive_state,
+                                    archive.len(),
+                                    delta,
+                                    config.wrap_navigation,
+                                );
+                            } else if let MenuItem::Stats = active_menu_item {
+                                move_selection(
+                                    &mut tag_state,
+                                    tag_counts(&db.tasks).len(),
+                                    delta,
+                                    config.wrap_navigation,
+                                );
+                            } else {
+                                let amount_tasks = view_indices(&db.tasks, &view_options).len();
+                                move_selection(
+                                    &mut task_list_state,
+                                    amount_tasks,
+                                    delta,
+                                    config.wrap_navigation,
+                                );
+                            }
+                        }
+                        None if config.quick_add && active_menu_item == MenuItem::Tasks => {
+                            if let KeyCode::Char(c) = event.code {
+                                if args.read_only {
+                                    error_message = Some(Error::ReadOnly.to_string());
+                                } else if !event.modifiers.contains(KeyModifiers::CONTROL) {
+                                    new_task.push(c);
+                                    show_pop_up = true;
+                                    input_mode = InputMode::Editing;
+                                }
+                            }
+                        }
+                        _ => {}
+                    },
+                    InputMode::Editing => match event.code {
+                        KeyCode::Enter if editing_task_id.is_some() => {
+                            let id = editing_task_id.expect("Editing a task.");
+                            let result = parse_estimate(&new_task_estimate).map(|estimate| {
+                                update_task(db_path, &mut db, id, &new_task, estimate)
+                            });
+                            match result {
+                                Err(e) => popup_error = Some(e),
+                                Ok(Err(e @ Error::EmptyTaskName)) => {
+                                    error_message = Some(e.to_string())
+                                }
+                                Ok(result) => {
+                                    match result {
+                                        Ok(()) => info_message = Some(String::from("Task updated")),
+                                        Err(e) => error_message = Some(e.to_string()),
+                                    }
+                                    select_task_by_id(
+                                        &db.tasks,
+                                        &mut task_list_state,
+                                        &view_options,
+                                        id,
+                                    );
+                                    editing_task_id = None;
+                                    input_mode = InputMode::Normal;
+                                    new_task = String::new();
+                                    new_task_estimate = String::new();
+                                    active_field = PopupField::Name;
+                                    show_pop_up = false;
+                                }
+                            }
+                        }
+                        KeyCode::Enter => match parse_due_date(&new_task_due)
+                            .and_then(|due_at| Ok((due_at, parse_estimate(&new_task_estimate)?)))
+                        {
+                            Ok((due_at, estimate)) => match add_task_to_db(
+                                db_path,
+                                &mut db,
+                                &new_task,
+                                due_at,
+                                estimate,
+                                new_task_parent,
+                            ) {
+                                Err(e @ Error::EmptyTaskName) => {
+                                    error_message = Some(e.to_string())
+                                }
+                                result => {
+                                    match result {
+                                        Ok(()) => info_message = Some(String::from("Task added")),
+                                        Err(e) => error_message = Some(e.to_string()),
+                                    }
+                                    input_mode = InputMode::Normal;
+                                    new_task = String::new();
+                                    new_task_due = String::new();
+                                    new_task_estimate = String::new();
+                                    new_task_parent = None;
+                                    active_field = PopupField::Name;
+                                    show_pop_up = false;
+                                }
+                            },
+                            Err(e) => popup_error = Some(e),
+                        },
+                        KeyCode::Tab => {
+                            active_field = match active_field {
+                                // Editing keeps the due date, so it skips that field.
+                                PopupField::Name if editing_task_id.is_some() => {
+                                    PopupField::Estimate
+                                }
+                                PopupField::Name => PopupField::Due,
+                                PopupField::Due => PopupField::Estimate,
+                                PopupField::Estimate => PopupField::Name,
+                            };
+                        }
+                        KeyCode::Char(c) => match active_field {
+                            PopupField::Name => new_task.push(c),
+                            PopupField::Due => new_task_due.push(c),
+                            PopupField::Estimate => new_task_estimate.push(c),
+                        },
+                        KeyCode::Backspace => {
+                            match active_field {
+                                PopupField::Name => new_task.pop(),
+                                PopupField::Due => new_task_due.pop(),
+                                PopupField::Estimate => new_task_estimate.pop(),
+                            };
+                        }
+                        KeyCode::Esc => {
+                            input_mode = InputMode::Normal;
+                            new_task = String::new();
+                            new_task_due = String::new();
+                            new_task_estimate = String::new();
+                            new_task_parent = None;
+                            active_field = PopupField::Name;
+                            editing_task_id = None;
+                            show_pop_up = false;
+                        }
+                        _ => {}
+                    },
+                    InputMode::Notes => match event.code {
+                        KeyCode::Char('s') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            let id = editing_task_id.take().expect("Editing notes.");
+                            match update_task_notes(db_path, &mut db, id, &notes) {
+                                Ok(()) => info_message = Some(String::from("Notes saved")),
+                                Err(e) => error_message = Some(e.to_string()),
+                            }
+                            notes.clear();
+                            input_mode = InputMode::Normal;
+                        }
+                        KeyCode::Enter => notes.push('\n'),
+                        KeyCode::Char(c) => notes.push(c),
+                        KeyCode::Backspace => {
+                            notes.pop();
+                        }
+                        KeyCode::Esc => {
+                            editing_task_id = None;
+                            notes.clear();
+                            input_mode = InputMode::Normal;
+                        }
+                        _ => {}
+                    },
+                    InputMode::RecoverDb => match event.code {
+                        KeyCode::Char('y') if args.read_only => {
+                            error_message = Some(Error::ReadOnly.to_string())
+                        }
+                        KeyCode::Char('y') => match backup_corrupt_db(db_path, format) {
+                            Ok(()) => {
+                                info_message =
+                                    Some(format!("Backed up the old DB to {}.corrupt", db_path));
+                                input_mode = InputMode::Normal;
+                            }
+                            Err(e) => error_message = Some(e.to_string()),
+                        },
+                        KeyCode::Char('n') | KeyCode::Char('q') | KeyCode::Esc => break,
+                        _ => {}
+                    },
+                    InputMode::Reminders => match event.code {
+                        KeyCode::Enter | KeyCode::Esc => input_mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::Log => match event.code {
+                        KeyCode::Down => log_scroll = log_scroll.saturating_add(1),
+                        KeyCode::Up => log_scroll = log_scroll.saturating_sub(1),
+                        KeyCode::Char('l') | KeyCode::Esc => input_mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::Tutorial => {
+                        input_mode = InputMode::Normal;
+                        active_menu_item = MenuItem::Tasks;
+                        if let Err(e) = set_config_value("tutorial_seen", "true") {
+                            error_message = Some(e.to_string());
+                        }
+                    }
+                    InputMode::Focus => match event.code {
+                        code if code == KeyCode::Esc
+                            || key_bindings.action(code) == Some(Action::Focus) =>
+                        {
+                            input_mode = InputMode::Normal
+                        }
+                        _ => {}
+                    },
+                    // Scrolling past the end is clamped when the help is drawn.
+                    InputMode::Help => match event.code {
+                        code if code == KeyCode::Esc
+                            || key_bindings.action(code) == Some(Action::Help) =>
+                        {
+                            input_mode = InputMode::Normal
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            help_scroll = help_scroll.saturating_add(1)
+                        }
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            help_scroll = help_scroll.saturating_sub(1)
+                        }
+                        KeyCode::PageDown => help_scroll = help_scroll.saturating_add(10),
+                        KeyCode::PageUp => help_scroll = help_scroll.saturating_sub(10),
+                        _ => {}
+                    },
+                    InputMode::ConfirmDelete => match event.code {
+                        KeyCode::Char('y') => {
+                            let result = if marked.is_empty() {
+                                remove_task_at_index(
+                                    db_path,
+                                    &mut db,
+                                    &mut task_list_state,
+                                    &view_options,
+                                )
+                            } else {
+                                remove_tasks(db_path, &mut db, &marked)
+                            };
+                            match result {
+                                Ok(removed) => {
+                                    info_message = Some(match removed.len() {
+                                        1 => String::from("Deleted 1 task"),
+                                        n => format!("Deleted {} tasks", n),
+                                    });
+                                    if !removed.is_empty() {
+                                        if deleted_tasks.len() >= UNDO_LIMIT {
+                                            deleted_tasks.remove(0);
+                                        }
+                                        deleted_tasks.push(removed);
+                                    }
+                                }
+                                Err(e) => error_message = Some(e.to_string()),
+                            }
+                            marked.clear();
+                            input_mode = InputMode::Normal;
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc => input_mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::ConfirmClear => match event.code {
+                        KeyCode::Char('y') => {
+                            match clear_completed(db_path, &mut db, &mut archive) {
+                                Ok(cleared) => {
+                                    info_message = Some(match cleared {
+                                        1 => String::from("Archived 1 completed task"),
+                                        n => format!("Archived {} completed tasks", n),
+                                    })
+                                }
+                                Err(e) => error_message = Some(e.to_string()),
+                            }
+                            marked.clear();
+                            let amount_tasks = view_indices(&db.tasks, &view_options).len();
+                            clamp_selection(&mut task_list_state, amount_tasks);
+                            input_mode = InputMode::Normal;
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc => input_mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::ReminderLead => match event.code {
+                        KeyCode::Enter => match parse_reminder_lead(&reminder_input) {
+                            Ok(lead) => {
+                                if let Some(id) = target_task_id {
+                                    if let Err(e) = set_reminder_lead(db_path, &mut db, id, lead) {
+                                        error_message = Some(e.to_string());
+                                    }
+                                }
+                                input_mode = InputMode::Normal;
+                            }
+                            Err(e) => error_message = Some(e),
+                        },
+                        KeyCode::Char(c) => reminder_input.push(c),
+                        KeyCode::Backspace => {
+                            reminder_input.pop();
+                        }
+                        KeyCode::Esc => input_mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::StartDate => match event.code {
+                        KeyCode::Enter => match parse_start_date(&start_input) {
+                            Ok(start_at) => {
+                                if let Some(id) = target_task_id {
+                                    if let Err(e) = set_start_at(db_path, &mut db, id, start_at) {
+                                        error_message = Some(e.to_string());
+                                    }
+                                }
+                                let amount_tasks = view_indices(&db.tasks, &view_options).len();
+                                clamp_selection(&mut task_list_state, amount_tasks);
+                                input_mode = InputMode::Normal;
+                            }
+                            Err(e) => error_message = Some(e),
+                        },
+                        KeyCode::Char(c) => start_input.push(c),
+                        KeyCode::Backspace => {
+                            start_input.pop();
+                        }
+                        KeyCode::Esc => input_mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::Snooze => match event.code {
+                        KeyCode::Char(c) => {
+                            let choice = c
+                                .to_digit(10)
+                                .and_then(|n| SNOOZE_CHOICES.get((n as usize).checked_sub(1)?));
+                            if let (Some((_, hours)), Some(id)) = (choice, target_task_id) {
+                                if let Err(e) =
+                                    snooze(db_path, &mut db, id, chrono::Duration::hours(*hours))
+                                {
+                                    error_message = Some(e.to_string());
+                                }
+                                input_mode = InputMode::Normal;
+                            }
+                        }
+                        KeyCode::Esc => input_mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::DateRange => match event.code {
+                        KeyCode::Enter => match parse_date_range(&date_range_input) {
+                            Ok(range) => {
+                                view_options.created = range;
+                                task_list_state.select(Some(0));
+                                input_mode = InputMode::Normal;
+                            }
+                            Err(e) => error_message = Some(e),
+                        },
+                        KeyCode::Char(c) => date_range_input.push(c),
+                        KeyCode::Backspace => {
+                            date_range_input.pop();
+                        }
+                        KeyCode::Esc => input_mode = InputMode::Normal,
+                        _ => {}
+                    },
+                    InputMode::Searching => match event.code {
+                        KeyCode::Enter => input_mode = InputMode::Normal,
+                        KeyCode::Char(c) => {
+                            view_options.filter.push(c);
+                            task_list_state.select(Some(0));
+                        }
+                        KeyCode::Backspace => {
+                            view_options.filter.pop();
+                            task_list_state.select(Some(0));
+                        }
+                        KeyCode::Esc => {
+                            input_mode = InputMode::Normal;
+                            view_options.filter.clear();
+                            task_list_state.select(Some(0));
+                        }
+                        _ => {}
+                    },
+                }
+                if let Some(message) = &error_message {
+                    push_log(&mut log, message, true);
+                }
+                if let Some(message) = &info_message {
+                    push_log(&mut log, message, false);
+                }
+            }
+            Event::Mouse(mouse) => {
+                if let (InputMode::Normal, MenuItem::Tasks) = (&input_mode, active_menu_item) {
+                    let view = view_indices(&db.tasks, &view_options);
+                    let amount_tasks = view.len();
+                    let hovered =
+                        list_row_at(list_area, view_options.dense, mouse.column, mouse.row)
+                            .and_then(|row| list_pages.task_at_row(list_offset, row))
+                            .filter(|&index| index < amount_tasks);
+                    match mouse.kind {
+                        MouseEventKind::Down(MouseButton::Left) => {
+                            if let Some(index) = hovered {
+                                task_list_state.select(Some(index));
+                                dragged = Some(db.tasks[view[index]].id);
+                            }
+                        }
+                        // The selection follows the pointer to show where the
+                        // task will land.
+                        MouseEventKind::Drag(MouseButton::Left) if dragged.is_some() => {
+                            if let Some(index) = hovered {
+                                task_list_state.select(Some(index));
+                            }
+                        }
+                        MouseEventKind::Up(MouseButton::Left) => {
+                            let from = dragged
+                                .take()
+                                .and_then(|id| view.iter().position(|&i| db.tasks[i].id == id));
+                            match (from, hovered) {
+                                (Some(from), Some(to)) if from != to => {
+                                    if args.read_only {
+                                        error_message = Some(Error::ReadOnly.to_string());
+                                    } else if view_options.sort_mode != SortMode::None {
+                                        error_message = Some(String::from(
+                                            "Clear the sort order to reorder tasks",
+                                        ));
+                                    } else if let Err(e) = drag_task(
+                                        db_path,
+                                        &mut db,
+                                        &mut task_list_state,
+                                        &view_options,
+                                        from,
+                                        to,
+                                    ) {
+                                        error_message = Some(e.to_string());
+                                    }
+                                }
+                                // Let go outside the list, the task stays put.
+                                (Some(from), None) => task_list_state.select(Some(from)),
+                                _ => {}
+                            }
+                        }
+                        MouseEventKind::ScrollDown => move_selection(
+                            &mut task_list_state,
+                            amount_tasks,
+                            1,
+                            config.wrap_navigation,
+                        ),
+                        MouseEventKind::ScrollUp => move_selection(
+                            &mut task_list_state,
+                            amount_tasks,
+                            -1,
+                            config.wrap_navigation,
+                        ),
+                        _ => {}
+                    }
+                } else {
+                    dragged = None;
+                }
+            }
+            Event::FileChanged if !matches!(input_mode, InputMode::RecoverDb) => {
+                // Our own writes are reported too.
+                if changed_on_disk(db_path, &db) {
+                    match reload_db(db_path, &mut db) {
+                        Ok(()) => {
+                            marked.clear();
+                            clamp_selection(
+                                &mut task_list_state,
+                                view_indices(&db.tasks, &view_options).len(),
+                            );
+                            info_message = Some(String::from("Reloaded changes made on disk"));
+                        }
+                        // The next save writes over the file anyway.
+                        Err(Error::UnsavedChanges) => push_log(
+                            &mut log,
+                            "The task file changed on disk while there were unsaved changes",
+                            true,
+                        ),
+                        Err(e) => error_message = Some(e.to_string()),
+                    }
+                }
+            }
+            Event::Resize | Event::Tick | Event::FileChanged => {}
+        }
+
+        if db
+            .modified_at
+            .is_some_and(|modified_at| modified_at.elapsed() >= SAVE_DELAY)
+        {
+            if let Err(e) = flush_db(db_path, &mut db) {
+                error_message = Some(e.to_string());
+            }
+            needs_redraw = true;
+        }
+
+        // Popping up only from normal mode leaves the reminder to fire once
+        // whatever is being typed is done.
+        if let InputMode::Normal | InputMode::Reminders = input_mode {
+            let due = due_reminders(&db.tasks, &mut reminded, Utc::now());
+            if !due.is_empty() {
+                reminders = due
+                    .iter()
+                    .map(|task| {
+                        let due_at = task.due_at.expect("Reminders have a due date.");
+                        format!("{} - due {}", task.name, to_local_string(&due_at))
+                    })
+                    .collect();
+                input_mode = InputMode::Reminders;
+                needs_redraw = true;
+            }
+            for task in due.iter().filter(|_| notify) {
+                let shown = notify_rust::Notification::new()
+                    .summary("Task due soon")
+                    .body(&task.name)
+                    .show();
+                if let Err(e) = shown {
+                    push_log(&mut log, &format!("Notifications disabled: {}", e), true);
+                    notify = false;
+                    break;
+                }
+            }
+        }
+
+        if notify {
+            for task in newly_overdue(&db.tasks, &mut notified, Utc::now()) {
+                let shown = notify_rust::Notification::new()
+                    .summary("Task overdue")
+                    .body(&task.name)
+                    .show();
+                // Not every desktop has a notification service; stop trying
+                // after the first failure rather than erroring every tick.
+                if let Err(e) = shown {
+                    push_log(&mut log, &format!("Notifications disabled: {}", e), true);
+                    notify = false;
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some((id, started)) = timer {
+        log_time(db_path, &mut db, id, started)?;
+    }
+    flush_db(db_path, &mut db)?;
+    if !args.read_only {
+        write_state(
+            db_path,
+            &UiState {
+                selected: task_list_state.selected().unwrap_or(0),
+            },
+        )?;
+    }
+    Ok(())
+}
+
+fn render_home<'a>(tasks: &[Task], theme: &Theme) -> (Paragraph<'a>, Gauge<'a>) {
+    let home = Paragraph::new(vec![
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Welcome")]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("to")]),
+        Spans::from(vec![Span::styled(
+            "todo-CLI",
+            Style::default().fg(theme.title),
+        )]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Press 't' top access the todo list")]),
+    ])
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.fg))
+            .title("Home")
+            .border_type(BorderType::Plain),
+    );
+
+    let ratio = completion_ratio(tasks);
+    let progress = Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.fg))
+                .title("Progress")
+                .border_type(BorderType::Plain),
+        )
+        .gauge_style(Style::default().fg(theme.accent))
+        .label(format!("{:.0}% complete", ratio * 100.0))
+        .ratio(ratio);
+    (home, progress)
+}
+
+/// The selected task alone, centered on a full screen of `height` rows.
+fn render_focus<'a>(task: Option<&Task>, height: u16, theme: &Theme) -> Paragraph<'a> {
+    let mut lines = vec![];
+    match task {
+        Some(task) => {
+            let mut name_style = Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD);
+            if task.completed_at.is_some() {
+                name_style = name_style.add_modifier(Modifier::CROSSED_OUT);
+            }
+            lines.push(Spans::from(Span::styled(task.name.clone(), name_style)));
+            lines.push(Spans::from(""));
+            let mut details = vec![format!("{} priority", task.priority)];
+            if let Some(due_at) = task.due_at {
+                details.push(format!("due {}", to_local_date(&due_at)));
+            }
+            details.extend(task.tags.iter().map(|tag| format!("#{}", tag)));
+            let detail_color = if is_overdue(task, Utc::now()) {
+                Color::Red
+            } else {
+                theme.info
+            };
+            lines.push(Spans::from(Span::styled(
+                details.join("  ·  "),
+                Style::default().fg(detail_color),
+            )));
+            if !task.notes.is_empty() {
+                lines.push(Spans::from(""));
+                lines.extend(task.notes.lines().map(|line| Spans::from(line.to_string())));
+            }
+        }
+        None => lines.push(Spans::from(Span::styled(
+            "Nothing to focus",
+            Style::default().fg(theme.info),
+        ))),
+    }
+    let padding = (height.saturating_sub(2) as usize).saturating_sub(lines.len()) / 2;
+    let mut centered = vec![Spans::from(""); padding];
+    centered.extend(lines);
+    Paragraph::new(centered)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.fg))
+                .title("Focus")
+                .border_type(BorderType::Plain),
+        )
+}
+
+fn help_lines<'a>(key_bindings: &KeyBindings, theme: &Theme) -> Vec<Spans<'a>> {
+    let mut lines: Vec<Spans> = key_bindings
+        .bindings
+        .iter()
+        .filter(|(_, codes)| !codes.is_empty())
+        .map(|&(action, _)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("{:<12}", key_bindings.label(action)),
+                    Style::default().fg(theme.accent),
+                ),
+                Span::raw(action.description()),
+            ])
+        })
+        .collect();
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "In a popup, Enter confirms and closes it instead.",
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines.push(Spans::from(Span::styled(
+        "Alt+letter jumps to the next task starting with that letter.",
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines
+}
+
+/// How far the help can scroll in `area` before its last line comes into
+/// view.
+fn help_max_scroll(lines: usize, area: Rect) -> u16 {
+    (lines as u16).saturating_sub(area.height.saturating_sub(2))
+}
+
+fn render_help<'a>(lines: Vec<Spans<'a>>, scroll: u16, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(lines).scroll((scroll, 0)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.fg))
+            .title("Help (j/k or PgUp/PgDn to scroll)")
+            .border_type(BorderType::Plain),
+    )
+}
+
+/// The first-run overview of the basics, using whatever keys they are bound
+/// to.
+fn tutorial_lines<'a>(key_bindings: &KeyBindings, theme: &Theme) -> Vec<Spans<'a>> {
+    let step = |number: usize, action: Action, text: &'a str| {
+        Spans::from(vec![
+            Span::raw(format!("{}. Press ", number)),
+            Span::styled(
+                key_bindings.label(action),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(text),
+        ])
+    };
+    vec![
+        Spans::from("Welcome! Here are the basics to get started."),
+        Spans::from(""),
+        step(
+            1,
+            Action::AddTask,
+            ", type a name and press Enter to add a task.",
+        ),
+        step(2, Action::Complete, " to mark the selected task done."),
+        step(
+            3,
+            Action::DeleteTask,
+            " and confirm with y to delete the selected task.",
+        ),
+        step(4, Action::Help, " any time to see every key."),
+        Spans::from(""),
+        Spans::from(Span::styled(
+            "Press any key to start.",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ]
+}
+
+fn render_tutorial<'a>(lines: Vec<Spans<'a>>, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.fg))
+            .title("Getting started")
+            .border_type(BorderType::Plain),
+    )
+}
+
+/// What happened over the past week, for a weekly review.
+#[derive(Debug, PartialEq)]
+struct Summary {
+    created: usize,
+    completed: usize,
+    /// Open tasks past their due date now, however long ago that was.
+    overdue: usize,
+}
+
+fn weekly_summary(tasks: &[Task], now: DateTime<Utc>) -> Summary {
+    let week_ago = now - chrono::Duration::days(7);
+    let this_week = |at: DateTime<Utc>| at > week_ago && at <= now;
+    Summary {
+        created: tasks
+            .iter()
+            .filter(|task| this_week(task.created_at))
+            .count(),
+        completed: tasks
+            .iter()
+            .filter(|task| task.completed_at.is_some_and(this_week))
+            .count(),
+        overdue: tasks.iter().filter(|task| is_overdue(task, now)).count(),
+    }
+}
+
+fn render_weekly_summary<'a>(summary: &Summary, theme: &Theme) -> Table<'a> {
+    let row = |label: &'a str, count: usize, color: Color| {
+        Row::new(vec![
+            Cell::from(label),
+            Cell::from(Span::styled(count.to_string(), Style::default().fg(color))),
+        ])
+    };
+    Table::new(vec![
+        row("Created", summary.created, theme.fg),
+        row("Completed", summary.completed, Color::Green),
+        row("Still overdue", summary.overdue, Color::Red),
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.fg))
+            .title("Past 7 days")
+            .border_type(BorderType::Plain),
+    )
+    .widths(&[Constraint::Length(14), Constraint::Length(6)])
+}
+
+fn completed_today(tasks: &[Task], today: NaiveDate) -> usize {
+    tasks
+        .iter()
+        .filter_map(|task| task.completed_at)
+        .filter(|at| at.with_timezone(&Local).date_naive() == today)
+        .count()
+}
+
+fn render_daily_goal<'a>(
+    tasks: &[Task],
+    goal: usize,
+    today: NaiveDate,
+    theme: &Theme,
+) -> Gauge<'a> {
+    let done = completed_today(tasks, today);
+    let (label, color) = if done >= goal {
+        (
+            format!("{} of {} today, goal reached!", done, goal),
+            Color::Green,
+        )
+    } else {
+        (format!("{} of {} today", done, goal), theme.accent)
+    };
+    Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.fg))
+                .title("Daily goal")
+                .border_type(BorderType::Plain),
+        )
+        .gauge_style(Style::default().fg(color))
+        .label(label)
+        .ratio(match goal {
+            0 => 1.0,
+            goal => (done as f64 / goal as f64).min(1.0),
+        })
+}
+
+/// Counts consecutive days with at least one completion, walking back from
+/// `today`. A streak that ended yesterday still counts until today is over.
+fn current_streak(tasks: &[Task], today: NaiveDate) -> u32 {
+    let days: HashSet<NaiveDate> = tasks
+        .iter()
+        .filter_map(|task| task.completed_at)
+        .map(|at| at.with_timezone(&Local).date_naive())
+        .collect();
+    let mut day = today;
+    if !days.contains(&day) {
+        day = match day.pred_opt() {
+            Some(yesterday) => yesterday,
+            None => return 0,
+        };
+    }
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        match day.pred_opt() {
+            Some(previous) => day = previous,
+            None => break,
+        }
+    }
+    streak
+}
+
+fn completion_ratio(tasks: &[Task]) -> f64 {
+    let completed = tasks
+        .iter()
+        .filter(|task| task.completed_at.is_some())
+        .count();
+    match tasks.len() {
+        0 => 0.0,
+        total => completed as f64 / total as f64,
+    }
+}
+
+fn render_stats<'a>(tasks: &[Task], daily_goal: Option<usize>, theme: &Theme) -> Paragraph<'a> {
+    let total = tasks.len();
+    let completed = tasks
+        .iter()
+        .filter(|task| task.completed_at.is_some())
+        .count();
+    let percentage = completion_ratio(tasks) * 100.0;
+    let today = Local::now().date_naive();
+    let streak = current_streak(tasks, today);
+
+    let mut lines = vec![
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw(format!("Total tasks: {}", total))]),
+        Spans::from(vec![Span::styled(
+            format!("Completed: {}", completed),
+            Style::default().fg(Color::Green),
+        )]),
+        Spans::from(vec![Span::styled(
+            format!("Active: {}", total - completed),
+            Style::default().fg(Color::Yellow),
+        )]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::styled(
+            format!("{:.0}% complete", percentage),
+            Style::default().fg(theme.title),
+        )]),
+        Spans::from(vec![Span::styled(
+            format!(
+                "Current streak: {} day{}",
+                streak,
+                if streak == 1 { "" } else { "s" }
+            ),
+            Style::default().fg(theme.accent),
+        )]),
+    ];
+    if let Some(goal) = daily_goal {
+        let done = completed_today(tasks, today);
+        lines.push(Spans::from(vec![Span::raw("")]));
+        lines.push(Spans::from(vec![Span::raw(format!(
+            "Completed today: {} of {}",
+            done, goal
+        ))]));
+        if done >= goal {
+            lines.push(Spans::from(vec![Span::styled(
+                "Daily goal reached, well done!",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            )]));
+        }
+    }
+
+    let stats = Paragraph::new(lines).alignment(Alignment::Center).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.fg))
+            .title("Stats")
+            .border_type(BorderType::Plain),
+    );
+    stats
+}
+
+fn render_archive<'a>(archive: &[Task], theme: &Theme) -> List<'a> {
+    let items: Vec<_> = archive
+        .iter()
+        .map(|task| {
+            let completed = task
+                .completed_at
+                .map(|completed_at| format!(" (completed {})", to_local_string(&completed_at)))
+                .unwrap_or_default();
+            ListItem::new(Spans::from(vec![
+                Span::raw(task.name.clone()),
+                Span::styled(completed, Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.fg))
+                .title("Archive")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight_bg)
+                .fg(theme.highlight_fg)
+                .add_modifier(Modifier::BOLD),
+        )
+}
+
+/// Every tag in use with how many tasks carry it, most used first.
+fn tag_counts(tasks: &[Task]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for tag in tasks.iter().flat_map(|task| &task.tags) {
+        *counts.entry(tag.clone()).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+fn render_tag_counts<'a>(counts: &[(String, usize)], theme: &Theme) -> List<'a> {
+    let items: Vec<_> = counts
+        .iter()
+        .map(|(tag, count)| {
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:>4} ", count), Style::default().fg(theme.accent)),
+                Span::raw(format!("#{}", tag)),
+            ]))
+        })
+        .collect();
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.fg))
+                .title("Tags (Enter to filter)")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight_bg)
+                .fg(theme.highlight_fg)
+                .add_modifier(Modifier::BOLD),
+        )
+}
+
+/// Titles the status bar when it has no message to show.
+fn footer_text() -> String {
+    format!("todo-CLI {}", Utc::now().year())
+}
+
+fn status_line(tasks: &[Task], view_options: &ViewOptions, input_mode: &InputMode) -> String {
+    let completed = tasks
+        .iter()
+        .filter(|task| task.completed_at.is_some())
+        .count();
+    format!(
+        "{} tasks: {} active, {} completed | Filter: {} | Sort: {} | {}",
+        tasks.len(),
+        tasks.len() - completed,
+        completed,
+        view_options.task_filter.label().unwrap_or("all"),
+        view_options.sort_mode.label().unwrap_or("manual"),
+        input_mode.label(),
+    )
+}
+
+fn init_db(db_path: &str, format: DbFormat) -> Result<(), Error> {
+    let path = Path::new(db_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if !path.exists() {
+        let db = Database {
+            format,
+            ..Database::default()
+        };
+        write_db(db_path, &db)?;
+    }
+    Ok(())
+}
+
+fn archive_path(db_path: &str) -> PathBuf {
+    Path::new(db_path).with_file_name(ARCHIVE_FILE)
+}
+
+fn read_archive(db_path: &str) -> Result<Vec<Task>, Error> {
+    let path = archive_path(db_path);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let archive_content = fs::read_to_string(path)?;
+    let parsed: Vec<Task> = serde_json::from_str(&archive_content)?;
+    Ok(parsed)
+}
+
+fn write_archive(db_path: &str, archive: &[Task]) -> Result<(), Error> {
+    write_atomically(&archive_path(db_path), &serde_json::to_vec(archive)?)
+}
+
+fn state_path(db_path: &str) -> PathBuf {
+    Path::new(db_path).with_file_name(STATE_FILE)
+}
+
+fn read_state(db_path: &str) -> UiState {
+    fs::read_to_string(state_path(db_path))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_state(db_path: &str, state: &UiState) -> Result<(), Error> {
+    fs::write(state_path(db_path), serde_json::to_vec(state)?)?;
+    Ok(())
+}
+
+fn read_db(db_path: &str, format: DbFormat) -> Result<Database, Error> {
+    let db_content = fs::read_to_string(db_path)?;
+    let (db, version) = parse_db(&db_content, format)?;
+    if version < DB_VERSION {
+        write_db(db_path, &db)?;
+    }
+    Ok(db)
+}
+
+/// Moves an unreadable database aside to `<db>.corrupt` and replaces it with
+/// an empty one, so the broken file is kept for manual recovery.
+fn backup_corrupt_db(db_path: &str, format: DbFormat) -> Result<(), Error> {
+    fs::rename(db_path, format!("{}.corrupt", db_path))?;
+    let db = Database {
+        format,
+        ..Database::default()
+    };
+    write_db(db_path, &db)
+}
+
+/// Parses a database in any known format, migrating it to the current one.
+/// Also returns the version it was stored as, so callers can rewrite it.
+fn parse_db(content: &str, format: DbFormat) -> Result<(Database, u32), serde_json::Error> {
+    let value = format.deserialize(content)?;
+    let version = match &value {
+        serde_json::Value::Array(_) => 0,
+        value => match value.get("version") {
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| serde::de::Error::custom("invalid database version"))?,
+            None => 1,
+        },
+    };
+    if version > DB_VERSION {
+        return Err(serde::de::Error::custom(format!(
+            "database version {} is newer than this build supports ({})",
+            version, DB_VERSION
+        )));
+    }
+    let db = Database {
+        format,
+        ..serde_json::from_value(migrate(value, version)?)?
+    };
+    Ok((db, version))
+}
+
+/// Upgrades a stored database one version at a time until it matches
+/// `DB_VERSION`:
+///
+/// - 0: a bare array of tasks
+/// - 1: `{ next_id, tasks }`
+/// - 2: `{ version, next_id, tasks }`
+/// - 3: tasks have a `uuid`, which older ones are given as they are read
+fn migrate(
+    mut value: serde_json::Value,
+    from_version: u32,
+) -> Result<serde_json::Value, serde_json::Error> {
+    for version in from_version..DB_VERSION {
+        value = match version {
+            0 => serde_json::to_value(Database::from_legacy(serde_json::from_value(value)?))?,
+            _ => {
+                if let Some(fields) = value.as_object_mut() {
+                    fields.insert(String::from("version"), serde_json::json!(version + 1));
+                }
+                value
+            }
+        };
+    }
+    Ok(value)
+}
+
+/// Appends `incoming` under fresh ids, skipping tasks whose uuid already
+/// exists. Tasks from `legacy` files got their uuids when read, so those are
+/// matched on name and creation time instead. Returns how many were added.
+fn merge_tasks(db: &mut Database, incoming: Vec<Task>, legacy: bool) -> usize {
+    let first_new = db.tasks.len();
+    let mut new_ids = HashMap::new();
+    for task in incoming {
+        let duplicate = db.tasks.iter().find(|existing| {
+            if legacy {
+                existing.name == task.name && existing.created_at == task.created_at
+            } else {
+                existing.uuid == task.uuid
+            }
+        });
+        match duplicate {
+            Some(existing) => {
+                new_ids.insert(task.id, existing.id);
+            }
+            None => {
+                let id = db.allocate_id();
+                new_ids.insert(task.id, id);
+                db.tasks.push(Task { id, ..task });
+            }
+        }
+    }
+    // Subtasks follow their parent to its new id, or to the task it turned
+    // out to duplicate, and are detached if the parent was not in the file.
+    for task in &mut db.tasks[first_new..] {
+        task.parent_id = task
+            .parent_id
+            .and_then(|parent| new_ids.get(&parent).copied());
+    }
+    db.tasks.len() - first_new
+}
+
+/// Adds a task for every non-blank line of `text`, returning how many.
+fn add_lines(db: &mut Database, text: &str) -> usize {
+    let mut added = 0;
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let id = db.allocate_id();
+        db.tasks.push(Task::new(id, line));
+        added += 1;
+    }
+    added
+}
+
+fn write_db(db_path: &str, db: &Database) -> Result<(), Error> {
+    back_up_db(db_path)?;
+    let content = db.format.serialize(db)?;
+    write_atomically(Path::new(db_path), &content)?;
+    db.written.set(Some(content_hash(&content)));
+    Ok(())
+}
+
+fn content_hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether the task file now holds something other than what we last wrote
+/// or have in memory. Edits made since our last write, but before the
+/// watcher reports it, don't count.
+fn changed_on_disk(db_path: &str, db: &Database) -> bool {
+    match fs::read(db_path) {
+        Ok(on_disk) => {
+            Some(content_hash(&on_disk)) != db.written.get()
+                && db.format.serialize(db).ok() != Some(on_disk)
+        }
+        Err(_) => true,
+    }
+}
+
+/// Writes `db` straight away unless it is debounced, in which case it is only
+/// marked as modified for `flush_db` to pick up.
+fn save_db(db_path: &str, db: &mut Database) -> Result<(), Error> {
+    if db.debounce {
+        db.modified_at = Some(Instant::now());
+        Ok(())
+    } else {
+        write_db(db_path, db)
+    }
+}
+
+/// Sends `Event::FileChanged` whenever the task file at `db_path` changes,
+/// once a burst of changes has settled. Its directory is watched rather than
+/// the file itself, because atomic writes replace the file.
+fn watch_db<I: Send + 'static>(
+    db_path: &str,
+    tx: mpsc::Sender<Event<I>>,
+) -> notify::Result<notify::RecommendedWatcher> {
+    use notify::Watcher;
+    let path = Path::new(db_path);
+    let file_name = path.file_name().map(|name| name.to_os_string());
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let (changes_tx, changes_rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        if let Ok(event) = result {
+            let db_changed = event
+                .paths
+                .iter()
+                .any(|changed| changed.file_name() == file_name.as_deref());
+            if db_changed && !event.kind.is_access() {
+                let _ = changes_tx.send(());
+            }
+        }
+    })?;
+    watcher.watch(&dir, notify::RecursiveMode::NonRecursive)?;
+    thread::spawn(move || {
+        while changes_rx.recv().is_ok() {
+            while changes_rx.recv_timeout(FILE_CHANGE_DELAY).is_ok() {}
+            if tx.send(Event::FileChanged).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(watcher)
+}
+
+/// Replaces `db` with what is on disk, for edits made outside the app. The
+/// file was migrated when first loaded, so it is only parsed here.
+fn reload_db(db_path: &str, db: &mut Database) -> Result<(), Error> {
+    if db.modified_at.is_some() {
+        return Err(Error::UnsavedChanges);
+    }
+    let (reloaded, _) = parse_db(&fs::read_to_string(db_path)?, db.format)?;
+    *db = Database {
+        format: db.format,
+        debounce: db.debounce,
+        ..reloaded
+    };
+    Ok(())
+}
+
+/// `$EDITOR`, or the usual default for the platform when it is not set.
+fn editor_command() -> String {
+    env::var("EDITOR")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from(if cfg!(windows) { "notepad" } else { "vi" }))
+}
+
+/// Hands the task file to an external editor and loads the result, putting
+/// the previous contents back if the edit no longer parses.
+fn edit_db_externally(db_path: &str, db: &mut Database) -> Result<(), Error> {
+    flush_db(db_path, db)?;
+    back_up_db(db_path)?;
+    let original = fs::read(db_path)?;
+    let editor = editor_command();
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(db_path)
+        .status()
+        .map_err(|e| Error::EditorFailed(format!("'{}': {}", editor, e)))?;
+    if !status.success() {
+        write_atomically(Path::new(db_path), &original)?;
+        return Err(Error::EditorFailed(format!("'{}': {}", editor, status)));
+    }
+    if let Err(e) = parse_db(&fs::read_to_string(db_path)?, db.format) {
+        write_atomically(Path::new(db_path), &original)?;
+        return Err(Error::InvalidEdit(e));
+    }
+    reload_db(db_path, db)
+}
+
+/// Writes any changes still waiting to be saved.
+fn flush_db(db_path: &str, db: &mut Database) -> Result<(), Error> {
+    if db.modified_at.is_some() {
+        write_db(db_path, db)?;
+        db.modified_at = None;
+    }
+    Ok(())
+}
+
+fn backup_dir(db_path: &str) -> PathBuf {
+    Path::new(db_path).with_file_name(BACKUP_DIR)
+}
+
+/// Backups of the database at `db_path`, oldest first.
+fn list_backups(db_path: &str) -> Result<Vec<PathBuf>, Error> {
+    let dir = backup_dir(db_path);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let prefix = format!("{}-", backup_stem(db_path));
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix))
+        })
+        .collect();
+    // Timestamps are fixed width, so names sort in the order they were made.
+    backups.sort();
+    Ok(backups)
+}
+
+fn backup_stem(db_path: &str) -> String {
+    Path::new(db_path).file_stem().map_or_else(
+        || String::from("db"),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+/// Copies the database as it is before a write to
+/// `backups/<name>-<timestamp>.<ext>` next to it.
+fn back_up_db(db_path: &str) -> Result<(), Error> {
+    let path = Path::new(db_path);
+    if !path.exists() {
+        return Ok(());
+    }
+    let dir = backup_dir(db_path);
+    fs::create_dir_all(&dir)?;
+    let mut name = format!(
+        "{}-{}",
+        backup_stem(db_path),
+        Utc::now().format("%Y%m%d-%H%M%S%3f")
+    );
+    if let Some(extension) = path.extension() {
+        name = format!("{}.{}", name, extension.to_string_lossy());
+    }
+    fs::copy(path, dir.join(name))?;
+    rotate_backups(db_path, BACKUP_LIMIT)
+}
+
+/// Deletes all but the `keep` most recent backups.
+fn rotate_backups(db_path: &str, keep: usize) -> Result<(), Error> {
+    let backups = list_backups(db_path)?;
+    for backup in &backups[..backups.len().saturating_sub(keep)] {
+        fs::remove_file(backup)?;
+    }
+    Ok(())
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let tmp_path = format!("{}.tmp", path.display());
+    if let Err(e) = fs::write(&tmp_path, content).and_then(|_| fs::rename(&tmp_path, path)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+fn render_todo<'a>(
+    task_list: &[Task],
+    task_list_state: &mut ListState,
+    view_options: &ViewOptions,
+    marked: &HashSet<usize>,
+    dragged: Option<usize>,
+    area: Rect,
+    theme: &Theme,
+) -> (List<'a>, Table<'a>, Paragraph<'a>, ListPages) {
+    let view = view_indices(task_list, view_options);
+    clamp_selection(task_list_state, view.len());
+    let width = list_inner(area, view_options.dense).width as usize;
+    let page_size = page_size(area, view_options.dense);
+    let mut labels = vec![];
+    if let Some(label) = view_options.task_filter.label() {
+        labels.push(label.to_string());
+    }
+    if let Some(label) = view_options.sort_mode.label() {
+        labels.push(label.to_string());
+    }
+    if let Some(tag) = &view_options.tag {
+        labels.push(format!("#{}", tag));
+    }
+    if let Some(range) = &view_options.created {
+        labels.push(range.label());
+    }
+    if view_options.show_deferred {
+        labels.push(String::from("+deferred"));
+    }
+    if view_options.sink_completed {
+        labels.push(String::from("done last"));
+    }
+    if !view_options.filter.is_empty() {
+        labels.push(format!("/{}", view_options.filter));
+    }
+    let now = Utc::now();
+    // Flags and marks get a column of their own whenever any task shows one,
+    // so the names still line up.
+    let flag_column = view.iter().any(|&index| task_list[index].flag.is_some());
+    let mark_column = view
+        .iter()
+        .any(|&index| marked.contains(&task_list[index].id));
+    let lines: Vec<Vec<Spans>> = view
+        .iter()
+        .map(|&index| {
+            let task = &task_list[index];
+            let (checkbox, style) = match task.completed_at {
+                Some(_) => (
+                    "[x] ",
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::CROSSED_OUT),
+                ),
+                None if view_options.age_colors => {
+                    ("[ ] ", Style::default().fg(age_color(task.created_at, now)))
+                }
+                None => ("[ ] ", Style::default().fg(task.priority.color())),
+            };
+            let deferred = is_deferred(task, now);
+            let style = if dragged == Some(task.id) {
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            } else if deferred {
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC)
+            } else {
+                style
+            };
+            let mut spans = vec![];
+            match task.flag {
+                Some(flag) => spans.push(Span::styled("● ", Style::default().fg(flag.color()))),
+                None if flag_column => spans.push(Span::raw("  ")),
+                None => {}
+            }
+            if marked.contains(&task.id) {
+                spans.push(Span::styled(
+                    "✓ ",
+                    Style::default()
+                        .fg(theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            } else if mark_column {
+                spans.push(Span::raw("  "));
+            }
+            if task.parent_id.is_some() {
+                spans.push(Span::raw("  "));
+            }
+            spans.push(Span::raw(checkbox));
+            // Wrapped lines line up under the name rather than the checkbox.
+            let indent = spans.iter().map(|span| span.width()).sum();
+            if view_options.filter.is_empty() {
+                spans.push(Span::styled(task.name.clone(), style));
+            } else {
+                let highlight = style
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+                let name = highlight_match(&task.name, &view_options.filter, style, highlight);
+                // A task found only through its notes says so.
+                let notes_only = !name.0.iter().any(|span| span.style == highlight)
+                    && notes_match(task, &view_options.filter);
+                spans.extend(name.0);
+                if notes_only {
+                    spans.push(Span::styled(
+                        " (notes)",
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+            }
+            if let Some(start_at) = task.start_at.filter(|_| deferred) {
+                spans.push(Span::styled(
+                    format!(" (starts {})", to_local_date(&start_at)),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            let (done, total) = child_progress(task.id, task_list);
+            if total > 0 {
+                // A parent marked done with open subtasks needs a second look.
+                if task.completed_at.is_some() && done < total {
+                    spans.push(Span::styled(
+                        format!(" ({}/{}) !", done, total),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ));
+                } else {
+                    spans.push(Span::styled(
+                        format!(" ({}/{})", done, total),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+            }
+            if let (Some(due_at), None) = (task.due_at, task.completed_at) {
+                let color = if due_at < now {
+                    Color::Red
+                } else {
+                    Color::DarkGray
+                };
+                spans.push(Span::styled(
+                    format!(" {}", format_countdown(due_at, now)),
+                    Style::default().fg(color),
+                ));
+            }
+            for tag in &task.tags {
+                spans.push(Span::styled(
+                    format!(" #{}", tag),
+                    Style::default().fg(Color::Magenta),
+                ));
+            }
+            let mut lines = wrap_spans(&spans, width, indent);
+            lines.truncate(page_size);
+            lines
+        })
+        .collect();
+    let pages = ListPages::new(lines.iter().map(Vec::len).collect(), page_size);
+    let selected = task_list_state.selected().unwrap_or(0);
+    let start = pages.start_of(selected);
+    if pages.starts.len() > 1 {
+        labels.push(format!(
+            "page {}/{}",
+            pages.page_of(selected) + 1,
+            pages.starts.len()
+        ));
+    }
+    let title = if labels.is_empty() {
+        String::from("Todo list")
+    } else {
+        format!("Todo list ({})", labels.join(", "))
+    };
+    let tasks = task_block(title, view_options.dense, theme);
+
+    let end = pages.starts.get(pages.page_of(selected) + 1).copied();
+    let items: Vec<_> = lines
+        .into_iter()
+        .take(end.unwrap_or(view.len()))
+        .skip(start)
+        .map(ListItem::new)
+        .collect();
+
+    let selected_task = match task_list_state
+        .selected()
+        .and_then(|selected| view.get(selected))
+        .map(|&index| &task_list[index])
+    {
+        Some(task) => task.clone(),
+        None => Task::new(0, "Create some tasks!"),
+    };
+
+    let list = List::new(items).block(tasks).highlight_style(
+        Style::default()
+            .bg(theme.highlight_bg)
+            .fg(theme.highlight_fg)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    let overdue = is_overdue(&selected_task, now);
+    let task_detail = Table::new(vec![Row::new(vec![
+        Cell::from(Span::raw(selected_task.id.to_string())),
+        Cell::from(Span::raw(selected_task.name)),
+        Cell::from(Span::raw(if view_options.absolute_times {
+            to_local_string(&selected_task.created_at)
+        } else {
+            humanize(&selected_task.created_at)
+        })),
+        Cell::from(Span::raw(match selected_task.completed_at {
+            Some(completed_at) => to_local_string(&completed_at),
+            None => "".to_string(),
+        })),
+        Cell::from(match selected_task.due_at {
+            Some(due_at) => Span::styled(
+                to_local_date(&due_at),
+                if overdue {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default()
+                },
+            ),
+            None => Span::raw(""),
+        }),
+        Cell::from(Span::styled(
+            selected_task.priority.to_string(),
+            Style::default().fg(selected_task.priority.color()),
+        )),
+        Cell::from(Span::raw(match selected_task.recurrence {
+            Some(recurrence) => recurrence.to_string(),
+            None => "".to_string(),
+        })),
+        Cell::from(Span::raw(
+            match (selected_task.actual_minutes, selected_task.estimate_minutes) {
+                (Some(actual), Some(estimate)) => {
+                    format!("{}/{}", fmt_minutes(actual), fmt_minutes(estimate))
+                }
+                (Some(actual), None) => fmt_minutes(actual),
+                (None, Some(estimate)) => format!("-/{}", fmt_minutes(estimate)),
+                (None, None) => "".to_string(),
+            },
+        )),
+    ])])
+    .header(Row::new(vec![
+        Cell::from(Span::styled(
+            "ID",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Name",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Created At",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Completed At",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Due",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Priority",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Repeats",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Cell::from(Span::styled(
+            "Time",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+    ]))
+    .block(if view_options.dense {
+        Block::default().style(Style::default().fg(theme.fg))
+    } else {
+        task_block("Detail", false, theme)
+    })
+    .widths(&[
+        Constraint::Percentage(4),
+        Constraint::Percentage(17),
+        Constraint::Percentage(16),
+        Constraint::Percentage(16),
+        Constraint::Percentage(11),
+        Constraint::Percentage(9),
+        Constraint::Percentage(9),
+        Constraint::Percentage(16),
+    ]);
+    let notes = Paragraph::new(selected_task.notes)
+        .wrap(Wrap { trim: false })
+        .block(task_block("Notes", view_options.dense, theme));
+
+    (list, task_detail, notes, pages)
+}
+
+/// Dense blocks keep only their title, which takes the top row.
+fn task_block<'a>(title: impl Into<Spans<'a>>, dense: bool, theme: &Theme) -> Block<'a> {
+    let block = Block::default()
+        .style(Style::default().fg(theme.fg))
+        .title(title);
+    if dense {
+        block
+    } else {
+        block.borders(Borders::ALL).border_type(BorderType::Plain)
+    }
+}
+
+/// Where the task list's rows are drawn within `area`, below its title.
+fn list_inner(area: Rect, dense: bool) -> Rect {
+    if dense {
+        Rect {
+            y: area.y + area.height.min(1),
+            height: area.height.saturating_sub(1),
+            ..area
+        }
+    } else {
+        Block::default().borders(Borders::ALL).inner(area)
+    }
+}
+
+fn fmt_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h{}m", hours, minutes),
+    }
+}
+
+/// Overdue tasks not yet in `notified`, which they are then added to.
+fn newly_overdue<'a>(
+    tasks: &'a [Task],
+    notified: &mut HashSet<usize>,
+    now: DateTime<Utc>,
+) -> Vec<&'a Task> {
+    tasks
+        .iter()
+        .filter(|task| is_overdue(task, now) && notified.insert(task.id))
+        .collect()
+}
+
+/// Open tasks whose reminder lead has begun, skipping and then recording any
+/// already in `reminded` for their current due date.
+fn due_reminders<'a>(
+    tasks: &'a [Task],
+    reminded: &mut HashSet<(usize, DateTime<Utc>)>,
+    now: DateTime<Utc>,
+) -> Vec<&'a Task> {
+    tasks
+        .iter()
+        .filter(
+            |task| match (task.due_at, task.reminder_lead, task.completed_at) {
+                (Some(due_at), Some(lead), None) => {
+                    due_at - lead <= now && reminded.insert((task.id, due_at))
+                }
+                _ => false,
+            },
+        )
+        .collect()
+}
+
+fn is_deferred(task: &Task, now: DateTime<Utc>) -> bool {
+    task.start_at.is_some_and(|start_at| start_at > now)
+}
+
+fn is_overdue(task: &Task, now: DateTime<Utc>) -> bool {
+    match (task.due_at, task.completed_at) {
+        (Some(due_at), None) => due_at < now,
+        _ => false,
+    }
+}
+
+/// Incomplete tasks that are overdue or due within `within` from now,
+/// soonest first.
+fn upcoming_tasks(tasks: &[Task], within: chrono::Duration) -> Vec<&Task> {
+    let cutoff = Utc::now() + within;
+    let mut upcoming: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| task.completed_at.is_none())
+        .filter(|task| task.due_at.is_some_and(|due_at| due_at <= cutoff))
+        .collect();
+    upcoming.sort_by_key(|task| task.due_at);
+    upcoming
+}
+
+/// Due dates are compared on the local calendar, so a task due late in the
+/// evening is still "today" even if that is already tomorrow in UTC.
+fn is_due_today(task: &Task, now: DateTime<Local>) -> bool {
+    match task.due_at {
+        Some(due_at) => {
+            due_at.with_timezone(&Local).date_naive() == now.date_naive()
+                || (task.completed_at.is_none() && due_at < now)
+        }
+        None => false,
+    }
+}
+
+fn parse_due_date(input: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    parse_due(input, Local::now()).map(Some).ok_or_else(|| {
+        format!(
+            "Invalid due date '{}', try YYYY-MM-DD, tomorrow, +3d or next monday",
+            input
+        )
+    })
+}
+
+/// Like `parse_due_date`, but the task starts at the beginning of the day.
+fn parse_start_date(input: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    parse_day(input, Local::now())
+        .and_then(|date| local_time(date, 0, 0, 0))
+        .map(Some)
+        .ok_or_else(|| {
+            format!(
+                "Invalid start date '{}', try YYYY-MM-DD, tomorrow, +3d or next monday",
+                input
+            )
+        })
+}
+
+/// Reads a due date as the end of that day in local time. Besides
+/// `YYYY-MM-DD` it understands `today`, `tomorrow`, `+3d`, `+2w` and weekday
+/// names, optionally after `next`, which mean the coming one after today.
+fn parse_due(input: &str, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+    local_time(parse_day(input, now)?, 23, 59, 59)
+}
+
+fn local_time(date: NaiveDate, hour: u32, min: u32, sec: u32) -> Option<DateTime<Utc>> {
+    date.and_hms_opt(hour, min, sec)?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// The day an absolute or relative date names, counting from `now`.
+fn parse_day(input: &str, now: DateTime<Local>) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    let today = now.date_naive();
+    let date = match input.as_str() {
+        "today" => Some(today),
+        "tomorrow" => today.succ_opt(),
+        _ => {
+            if let Some(offset) = input.strip_prefix('+') {
+                let (count, unit) = offset.split_at(offset.len().saturating_sub(1));
+                let count: i64 = count.parse().ok()?;
+                match unit {
+                    "d" => today.checked_add_signed(chrono::Duration::days(count)),
+                    "w" => today.checked_add_signed(chrono::Duration::weeks(count)),
+                    _ => None,
+                }
+            } else if let Ok(weekday) = input
+                .strip_prefix("next ")
+                .unwrap_or(&input)
+                .parse::<chrono::Weekday>()
+            {
+                let ahead = (weekday.num_days_from_monday() as i64
+                    - today.weekday().num_days_from_monday() as i64)
+                    .rem_euclid(7);
+                let ahead = if ahead == 0 { 7 } else { ahead };
+                today.checked_add_signed(chrono::Duration::days(ahead))
+            } else {
+                NaiveDate::parse_from_str(&input, "%Y-%m-%d").ok()
+            }
+        }
+    }?;
+    Some(date)
+}
+
+fn parse_estimate(input: &str) -> Result<Option<u32>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    input
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("Invalid estimate '{}', expected a number of minutes", input))
+}
+
+/// Hours before the due date; left blank, the reminder is removed.
+fn parse_reminder_lead(input: &str) -> Result<Option<chrono::Duration>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    input
+        .parse::<u32>()
+        .map(|hours| Some(chrono::Duration::hours(hours.into())))
+        .map_err(|_| format!("Invalid reminder '{}', expected a number of hours", input))
+}
+
+fn set_start_at(
+    db_path: &str,
+    db: &mut Database,
+    id: usize,
+    start_at: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    if let Some(task) = db.tasks.iter_mut().find(|task| task.id == id) {
+        task.start_at = start_at;
+        save_db(db_path, db)?;
+    }
+    Ok(())
+}
+
+fn set_reminder_lead(
+    db_path: &str,
+    db: &mut Database,
+    id: usize,
+    lead: Option<chrono::Duration>,
+) -> Result<(), Error> {
+    if let Some(task) = db.tasks.iter_mut().find(|task| task.id == id) {
+        task.reminder_lead = lead;
+        save_db(db_path, db)?;
+    }
+    Ok(())
+}
+
+fn add_task_to_db(
+    db_path: &str,
+    db: &mut Database,
+    task_name: &str,
+    due_at: Option<DateTime<Utc>>,
+    estimate_minutes: Option<u32>,
+    parent_id: Option<usize>,
+) -> Result<(), Error> {
+    let (name, tags) = parse_tags(task_name.trim());
+    if name.is_empty() {
+        return Err(Error::EmptyTaskName);
+    }
+    let new_id = db.allocate_id();
+
+    db.tasks.push(Task {
+        due_at,
+        tags,
+        parent_id,
+        estimate_minutes,
+        ..Task::new(new_id, &name)
+    });
+    save_db(db_path, db)
+}
+
+/// Splits `#tag` tokens out of a task name, e.g. `buy milk #shopping`.
+fn parse_tags(input: &str) -> (String, Vec<String>) {
+    let mut words = vec![];
+    let mut tags: Vec<String> = vec![];
+    for word in input.split_whitespace() {
+        match word.strip_prefix('#') {
+            Some(tag) if !tag.is_empty() => {
+                if !tags.iter().any(|t| t == tag) {
+                    tags.push(tag.to_string());
+                }
+            }
+            _ => words.push(word),
+        }
+    }
+    (words.join(" "), tags)
+}
+
+fn next_tag(tasks: &[Task], current: Option<&str>) -> Option<String> {
+    let mut tags: Vec<&String> = tasks.iter().flat_map(|task| &task.tags).collect();
+    tags.sort();
+    tags.dedup();
+    let next = match current {
+        Some(current) => tags.iter().position(|tag| *tag == current).map(|i| i + 1),
+        None => Some(0),
+    };
+    next.and_then(|i| tags.get(i)).map(|tag| tag.to_string())
+}
+
+fn update_task(
+    db_path: &str,
+    db: &mut Database,
+    id: usize,
+    new_name: &str,
+    estimate_minutes: Option<u32>,
+) -> Result<(), Error> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err(Error::EmptyTaskName);
+    }
+    if let Some(task) = db.tasks.iter_mut().find(|task| task.id == id) {
+        task.name = new_name.to_string();
+        task.estimate_minutes = estimate_minutes;
+        save_db(db_path, db)?;
+    }
+    Ok(())
+}
+
+/// Adds the minutes elapsed since `started` to the task's tracked time.
+fn log_time(db_path: &str, db: &mut Database, id: usize, started: Instant) -> Result<u32, Error> {
+    let minutes = ((started.elapsed().as_secs() + 30) / 60) as u32;
+    if let Some(task) = db.tasks.iter_mut().find(|task| task.id == id) {
+        task.actual_minutes = Some(task.actual_minutes.unwrap_or(0) + minutes);
+        save_db(db_path, db)?;
+    }
+    Ok(minutes)
+}
+
+fn update_task_notes(
+    db_path: &str,
+    db: &mut Database,
+    id: usize,
+    notes: &str,
+) -> Result<(), Error> {
+    if let Some(task) = db.tasks.iter_mut().find(|task| task.id == id) {
+        task.notes = notes.to_string();
+        save_db(db_path, db)?;
+    }
+    Ok(())
+}
+
+/// Appends an open copy of the task with `id` and returns the copy's id.
+fn duplicate_task(db_path: &str, db: &mut Database, id: usize) -> Result<usize, Error> {
+    let original = match db.tasks.iter().find(|task| task.id == id) {
+        Some(task) => task.clone(),
+        None => return Ok(id),
+    };
+    let new_id = db.allocate_id();
+    db.tasks.push(Task {
+        id: new_id,
+        uuid: Uuid::new_v4(),
+        created_at: Utc::now(),
+        completed_at: None,
+        actual_minutes: None,
+        spawned_from: None,
+        ..original
+    });
+    save_db(db_path, db)?;
+    Ok(new_id)
+}
+
+fn selected_task<'a>(
+    tasks: &'a [Task],
+    task_list_state: &ListState,
+    view_options: &ViewOptions,
+) -> Option<&'a Task> {
+    selected_index(tasks, task_list_state, view_options).map(|index| &tasks[index])
+}
+
+fn select_task_by_id(
+    tasks: &[Task],
+    task_list_state: &mut ListState,
+    view_options: &ViewOptions,
+    id: usize,
+) {
+    let position = view_indices(tasks, view_options)
+        .iter()
+        .position(|&index| tasks[index].id == id);
+    if let Some(position) = position {
+        task_list_state.select(Some(position));
+    }
+}
+
+/// Deleting a task also deletes its subtasks. They are returned after the
+/// parent, so restoring the entries in reverse rebuilds the original list.
+fn remove_task_at_index(
+    db_path: &str,
+    db: &mut Database,
+    task_list_state: &mut ListState,
+    view_options: &ViewOptions,
+) -> Result<Vec<(usize, Task)>, Error> {
+    let mut removed: Vec<(usize, Task)> =
+        remove_selected_task(&mut db.tasks, task_list_state, view_options)
+            .into_iter()
+            .collect();
+    if let Some(parent_id) = removed.first().map(|(_, task)| task.id) {
+        for index in (0..db.tasks.len()).rev() {
+            if db.tasks[index].parent_id == Some(parent_id) {
+                removed.push((index, db.tasks.remove(index)));
+            }
+        }
+        save_db(db_path, db)?;
+    }
+    Ok(removed)
+}
+
+fn move_selected_task(
+    db_path: &str,
+    db: &mut Database,
+    task_list_state: &mut ListState,
+    view_options: &ViewOptions,
+    direction: isize,
+) -> Result<(), Error> {
+    let position = match task_list_state.selected() {
+        Some(position) => position,
+        None => return Ok(()),
+    };
+    if let Some(moved_to) = move_task(&mut db.tasks, view_options, position, direction) {
+        let id = db.tasks[moved_to].id;
+        save_db(db_path, db)?;
+        select_task_by_id(&db.tasks, task_list_state, view_options, id);
+    }
+    Ok(())
+}
+
+/// Moves the task shown at `from` in the list to where the one at `to` is,
+/// shifting the tasks in between.
+fn drag_task(
+    db_path: &str,
+    db: &mut Database,
+    task_list_state: &mut ListState,
+    view_options: &ViewOptions,
+    from: usize,
+    to: usize,
+) -> Result<(), Error> {
+    let view = view_indices(&db.tasks, view_options);
+    let (Some(&from), Some(&to)) = (view.get(from), view.get(to)) else {
+        return Ok(());
+    };
+    let id = db.tasks[from].id;
+    move_task_to(&mut db.tasks, from, to);
+    save_db(db_path, db)?;
+    select_task_by_id(&db.tasks, task_list_state, view_options, id);
+    Ok(())
+}
+
+/// Takes the task at `from` out and puts it back at `to`, both in storage
+/// order, so it lands after the task at `to` when moving down and before it
+/// when moving up.
+fn move_task_to(tasks: &mut Vec<Task>, from: usize, to: usize) {
+    let task = tasks.remove(from);
+    tasks.insert(to, task);
+}
+
+/// Moves the task at `position` in the view past its visible neighbour and
+/// returns where it now is in `tasks`. A top-level task moves past the whole
+/// neighbouring task and its subtasks, while a subtask only moves among its
+/// siblings. Moves the view would undo, such as past the completed tasks it
+/// sinks, are refused.
+fn move_task(
+    tasks: &mut [Task],
+    view_options: &ViewOptions,
+    position: usize,
+    direction: isize,
+) -> Option<usize> {
+    let view = view_indices(tasks, view_options);
+    let nested: Vec<bool> = view
+        .iter()
+        .map(|&i| {
+            tasks[i]
+                .parent_id
+                .is_some_and(|parent| view.iter().any(|&j| tasks[j].id == parent))
+        })
+        .collect();
+    let &from = view.get(position)?;
+    let mut target = position.checked_add_signed(direction)?;
+    if nested[position] {
+        let &sibling = view.get(target)?;
+        if tasks[sibling].parent_id != tasks[from].parent_id {
+            return None;
+        }
+    } else {
+        while nested.get(target) == Some(&true) {
+            target = target.checked_add_signed(direction)?;
+        }
+    }
+    let &to = view.get(target)?;
+
+    tasks.swap(from, to);
+    let moved = view_indices(tasks, view_options);
+    let place = |index| moved.iter().position(|&i| i == index);
+    if (place(to) < place(from)) != (direction < 0) {
+        tasks.swap(from, to);
+        return None;
+    }
+    Some(to)
+}
+
+/// Looks a task up by the id given on the command line.
+fn find_task<'a>(tasks: &'a [Task], id: &str) -> Result<&'a Task, Error> {
+    id.parse::<usize>()
+        .ok()
+        .and_then(|id| tasks.iter().find(|task| task.id == id))
+        .ok_or_else(|| Error::TaskNotFound(id.to_string()))
+}
+
+/// Removes every task in `ids` along with their subtasks, back to front so
+/// that restoring the returned entries in reverse puts each one back at its
+/// original index.
+fn remove_tasks(
+    db_path: &str,
+    db: &mut Database,
+    ids: &HashSet<usize>,
+) -> Result<Vec<(usize, Task)>, Error> {
+    let ids: HashSet<usize> = db
+        .tasks
+        .iter()
+        .filter(|task| {
+            ids.contains(&task.id) || task.parent_id.is_some_and(|parent| ids.contains(&parent))
+        })
+        .map(|task| task.id)
+        .collect();
+    let mut removed = vec![];
+    for index in (0..db.tasks.len()).rev() {
+        if ids.contains(&db.tasks[index].id) {
+            removed.push((index, db.tasks.remove(index)));
+        }
+    }
+    save_db(db_path, db)?;
+    Ok(removed)
+}
+
+/// The archive is written before the DB, so a failure part way through leaves
+/// the task in both files rather than in neither.
+fn archive_task(
+    db_path: &str,
+    db: &mut Database,
+    archive: &mut Vec<Task>,
+    task_list_state: &mut ListState,
+    view_options: &ViewOptions,
+) -> Result<(), Error> {
+    if let Some((_, task)) = remove_selected_task(&mut db.tasks, task_list_state, view_options) {
+        archive.push(task);
+        write_archive(db_path, archive)?;
+        save_db(db_path, db)?;
+    }
+    Ok(())
+}
+
+/// Moves every completed task to the archive. Returns how many were moved.
+/// Subtasks only go along with their parent, so a parent is kept while any
+/// of its subtasks are still open.
+fn clear_completed(
+    db_path: &str,
+    db: &mut Database,
+    archive: &mut Vec<Task>,
+) -> Result<usize, Error> {
+    let ids: HashSet<usize> = db.tasks.iter().map(|task| task.id).collect();
+    let parent_of = |task: &Task| task.parent_id.filter(|parent| ids.contains(parent));
+    let cleared_ids: HashSet<usize> = db
+        .tasks
+        .iter()
+        .filter(|task| parent_of(task).is_none() && task.completed_at.is_some())
+        .filter(|task| {
+            db.tasks.iter().all(|subtask| {
+                parent_of(subtask) != Some(task.id) || subtask.completed_at.is_some()
+            })
+        })
+        .map(|task| task.id)
+        .collect();
+    let (completed, open): (Vec<Task>, Vec<Task>) = db
+        .tasks
+        .drain(..)
+        .partition(|task| cleared_ids.contains(&parent_of(task).unwrap_or(task.id)));
+    db.tasks = open;
+    let cleared = completed.len();
+    if cleared > 0 {
+        archive.extend(completed);
+        write_archive(db_path, archive)?;
+        save_db(db_path, db)?;
+    }
+    Ok(cleared)
+}
+
+fn unarchive_task(
+    db_path: &str,
+    db: &mut Database,
+    archive: &mut Vec<Task>,
+    archive_state: &ListState,
+) -> Result<(), Error> {
+    match archive_state.selected() {
+        Some(selected) if selected < archive.len() => {
+            db.tasks.push(archive.remove(selected));
+            save_db(db_path, db)?;
+            write_archive(db_path, archive)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn remove_selected_task(
+    tasks: &mut Vec<Task>,
+    task_list_state: &mut ListState,
+    view_options: &ViewOptions,
+) -> Option<(usize, Task)> {
+    let view = view_indices(tasks, view_options);
+    match task_list_state.selected() {
+        Some(selected) if selected < view.len() => {
+            let index = view[selected];
+            let task = tasks.remove(index);
+            task_list_state.select(Some(selected.saturating_sub(1)));
+            Some((index, task))
+        }
+        _ => None,
+    }
+}
+
+/// Puts back the tasks one delete removed, in reverse so each lands at the
+/// index it was removed from.
+fn restore_tasks(
+    db_path: &str,
+    db: &mut Database,
+    removed: Vec<(usize, Task)>,
+) -> Result<(), Error> {
+    for (index, task) in removed.into_iter().rev() {
+        let index = index.min(db.tasks.len());
+        db.tasks.insert(index, task);
+    }
+    save_db(db_path, db)
+}
+
+fn complete_task(
+    db_path: &str,
+    db: &mut Database,
+    task_list_state: &ListState,
+    view_options: &ViewOptions,
+) -> Result<(), Error> {
+    let selected = match selected_index(&db.tasks, task_list_state, view_options) {
+        Some(index) => index,
+        None => return Ok(()),
+    };
+    toggle_task_at(db, selected);
+    save_db(db_path, db)
+}
+
+/// Completes every task in `ids` that is not already done.
+fn complete_tasks(db_path: &str, db: &mut Database, ids: &HashSet<usize>) -> Result<(), Error> {
+    let indices: Vec<usize> = (0..db.tasks.len())
+        .filter(|&i| ids.contains(&db.tasks[i].id) && db.tasks[i].completed_at.is_none())
+        .collect();
+    for index in indices {
+        toggle_task_at(db, index);
+    }
+    save_db(db_path, db)
+}
+
+/// Completing a recurring task also queues up its next occurrence, unless an
+/// earlier completion already did, and completing a parent completes any of
+/// its subtasks that are still open.
+fn toggle_task_at(db: &mut Database, selected: usize) {
+    let task = db.tasks[selected].clone().toggle_completed();
+    let already_spawned = db
+        .tasks
+        .iter()
+        .any(|other| other.spawned_from == Some(task.uuid));
+
+    let next_occurrence = match (task.completed_at, task.recurrence) {
+        (Some(_), Some(recurrence)) if !already_spawned => Some(Task {
+            id: db.allocate_id(),
+            uuid: Uuid::new_v4(),
+            created_at: recurrence.advance(task.created_at),
+            completed_at: None,
+            due_at: task.due_at.map(|due_at| recurrence.advance(due_at)),
+            start_at: task.start_at.map(|start_at| recurrence.advance(start_at)),
+            actual_minutes: None,
+            spawned_from: Some(task.uuid),
+            ..task.clone()
+        }),
+        _ => None,
+    };
+
+    if let Some(completed_at) = task.completed_at {
+        for subtask in db.tasks.iter_mut() {
+            if subtask.parent_id == Some(task.id) && subtask.completed_at.is_none() {
+                subtask.completed_at = Some(completed_at);
+            }
+        }
+    }
+    db.tasks[selected] = task;
+    if let Some(next_occurrence) = next_occurrence {
+        db.tasks.push(next_occurrence);
+    }
+}
+
+fn cycle_task_priority(
+    db_path: &str,
+    db: &mut Database,
+    task_list_state: &ListState,
+    view_options: &ViewOptions,
+) -> Result<(), Error> {
+    update_selected_task(
+        db_path,
+        db,
+        task_list_state,
+        view_options,
+        Task::cycle_priority,
+    )
+}
+
+fn cycle_task_flag(
+    db_path: &str,
+    db: &mut Database,
+    task_list_state: &ListState,
+    view_options: &ViewOptions,
+) -> Result<(), Error> {
+    update_selected_task(db_path, db, task_list_state, view_options, Task::cycle_flag)
+}
+
+fn cycle_task_recurrence(
+    db_path: &str,
+    db: &mut Database,
+    task_list_state: &ListState,
+    view_options: &ViewOptions,
+) -> Result<(), Error> {
+    update_selected_task(
+        db_path,
+        db,
+        task_list_state,
+        view_options,
+        Task::cycle_recurrence,
+    )
+}
+
+fn snooze(db_path: &str, db: &mut Database, id: usize, by: chrono::Duration) -> Result<(), Error> {
+    if let Some(task) = db.tasks.iter_mut().find(|task| task.id == id) {
+        *task = task.clone().snooze(by, Utc::now());
+    }
+    save_db(db_path, db)
+}
+
+fn update_selected_task(
+    db_path: &str,
+    db: &mut Database,
+    task_list_state: &ListState,
+    view_options: &ViewOptions,
+    update: impl FnOnce(Task) -> Task,
+) -> Result<(), Error> {
+    let selected = match selected_index(&db.tasks, task_list_state, view_options) {
+        Some(index) => index,
+        None => return Ok(()),
+    };
+    db.tasks[selected] = update(db.tasks[selected].clone());
+    save_db(db_path, db)
+}
+
+fn selected_index(
+    tasks: &[Task],
+    task_list_state: &ListState,
+    view_options: &ViewOptions,
+) -> Option<usize> {
+    task_list_state
+        .selected()
+        .and_then(|selected| view_indices(tasks, view_options).get(selected).copied())
+}
+
+/// Moves the selection by `delta`. It wraps around at either end when `wrap`
+/// is set and otherwise stops there.
+fn move_selection(task_list_state: &mut ListState, len: usize, delta: isize, wrap: bool) {
+    if let Some(selected) = task_list_state.selected() {
+        if len > 0 {
+            let next = selected as isize + delta;
+            let next = if wrap {
+                next.rem_euclid(len as isize)
+            } else {
+                next.clamp(0, len as isize - 1)
+            };
+            task_list_state.select(Some(next as usize));
+        }
+    }
+}
+
+/// How many tasks fit on one page of the bordered list `area`.
+fn page_size(area: Rect, dense: bool) -> usize {
+    (list_inner(area, dense).height as usize).max(1)
+}
+
+/// How the wrapped task list splits into pages: the rows each task takes and
+/// the index of the first task on every page.
+#[derive(Default)]
+struct ListPages {
+    heights: Vec<usize>,
+    starts: Vec<usize>,
+}
+
+impl ListPages {
+    /// Fills each page of `page_size` rows with as many whole tasks as fit.
+    fn new(heights: Vec<usize>, page_size: usize) -> Self {
+        let mut starts = vec![0];
+        let mut rows = 0;
+        for (index, &height) in heights.iter().enumerate() {
+            if rows + height > page_size && rows > 0 {
+                starts.push(index);
+                rows = 0;
+            }
+            rows += height;
+        }
+        ListPages { heights, starts }
+    }
+
+    /// Which page, counting from zero, holds the task at `index`.
+    fn page_of(&self, index: usize) -> usize {
+        self.starts
+            .partition_point(|&start| start <= index)
+            .saturating_sub(1)
+    }
+
+    /// Index of the first task on the page holding `index`.
+    fn start_of(&self, index: usize) -> usize {
+        self.starts.get(self.page_of(index)).copied().unwrap_or(0)
+    }
+
+    /// The task drawn on `row` of the page holding `index`.
+    fn task_at_row(&self, index: usize, row: usize) -> Option<usize> {
+        let mut top = 0;
+        for (task, &height) in self.heights.iter().enumerate().skip(self.start_of(index)) {
+            if row < top + height {
+                return Some(task);
+            }
+            top += height;
+        }
+        None
+    }
+}
+
+/// Word-wraps `spans` to lines of at most `width` columns, keeping their
+/// styles. Lines after the first start with `indent` spaces, and words too
+/// long for a line are split.
+fn wrap_spans<'a>(spans: &[Span], width: usize, indent: usize) -> Vec<Spans<'a>> {
+    let width = width.max(1);
+    let indent = if indent < width { indent } else { 0 };
+    let columns = |chars: &[(char, Style)]| -> usize {
+        chars.iter().map(|&(c, _)| c.width().unwrap_or(0)).sum()
+    };
+    let mut lines: Vec<Vec<(char, Style)>> = vec![];
+    let mut line: Vec<(char, Style)> = vec![];
+    let mut word: Vec<(char, Style)> = vec![];
+    let chars = spans
+        .iter()
+        .flat_map(|span| span.content.chars().map(move |c| (c, span.style)));
+    for (c, style) in chars.chain(std::iter::once((' ', Style::default()))) {
+        if c != ' ' {
+            word.push((c, style));
+            continue;
+        }
+        let limit = |lines: &Vec<_>| {
+            if lines.is_empty() {
+                width
+            } else {
+                width - indent
+            }
+        };
+        if !line.is_empty() && columns(&line) + columns(&word) > limit(&lines) {
+            while line.last().is_some_and(|&(c, _)| c == ' ') {
+                line.pop();
+            }
+            lines.push(std::mem::take(&mut line));
+        }
+        while columns(&line) + columns(&word) > limit(&lines) {
+            // Always takes a character, even one wider than the line.
+            let room = limit(&lines).saturating_sub(columns(&line));
+            let mut split = 0;
+            while split < word.len() && columns(&word[..=split]) <= room {
+                split += 1;
+            }
+            let rest = word.split_off(split.max(1));
+            line.append(&mut word);
+            lines.push(std::mem::take(&mut line));
+            word = rest;
+        }
+        line.append(&mut word);
+        if columns(&line) < limit(&lines) {
+            line.push((c, style));
+        }
+    }
+    while line.last().is_some_and(|&(c, _)| c == ' ') {
+        line.pop();
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(number, line)| {
+            let mut spans = vec![];
+            if number > 0 && indent > 0 {
+                spans.push(Span::raw(" ".repeat(indent)));
+            }
+            let mut run = String::new();
+            let mut run_style = None;
+            for (c, style) in line {
+                if run_style.is_some_and(|run_style| run_style != style) {
+                    spans.push(Span::styled(std::mem::take(&mut run), run_style.unwrap()));
+                }
+                run_style = Some(style);
+                run.push(c);
+            }
+            if let Some(style) = run_style {
+                spans.push(Span::styled(run, style));
+            }
+            Spans::from(spans)
+        })
+        .collect()
+}
+
+/// The row inside the bordered list `area` at the given terminal position.
+fn list_row_at(area: Rect, dense: bool, column: u16, row: u16) -> Option<usize> {
+    let inner = list_inner(area, dense);
+    let inside_x = column >= inner.x && column < inner.x + inner.width;
+    let inside_y = row >= inner.y && row < inner.y + inner.height;
+    if inside_x && inside_y {
+        Some((row - inner.y) as usize)
+    } else {
+        None
+    }
+}
+
+/// Keeps the selection inside the visible list so the `List` widget can
+/// scroll its offset to it.
+fn clamp_selection(task_list_state: &mut ListState, len: usize) {
+    match task_list_state.selected() {
+        Some(selected) if selected >= len => task_list_state.select(Some(len.saturating_sub(1))),
+        None if len > 0 => task_list_state.select(Some(0)),
+        _ => {}
+    }
+}
+
+fn jump_selection(task_list_state: &mut ListState, len: usize, to_end: bool) {
+    task_list_state.select(match len {
+        0 => None,
+        _ if to_end => Some(len - 1),
+        _ => Some(0),
+    });
+}
+
+/// Indices into `tasks` in the order they are displayed, so the stored
+/// order is left untouched by sorting.
+fn view_indices(tasks: &[Task], view_options: &ViewOptions) -> Vec<usize> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let scores: Vec<Option<i64>> = tasks
+        .iter()
+        .map(|task| view_options.score(&matcher, task))
+        .collect();
+    let mut indices: Vec<usize> = (0..tasks.len()).filter(|&i| scores[i].is_some()).collect();
+    match view_options.sort_mode {
+        SortMode::None => {}
+        SortMode::CreatedAsc => indices.sort_by_key(|&i| tasks[i].created_at),
+        SortMode::CreatedDesc => {
+            indices.sort_by(|&a, &b| tasks[b].created_at.cmp(&tasks[a].created_at))
+        }
+        SortMode::Completed => {
+            indices.sort_by_key(|&i| (tasks[i].completed_at.is_none(), tasks[i].completed_at))
+        }
+        SortMode::Priority => {
+            indices.sort_by_key(|&i| (std::cmp::Reverse(tasks[i].priority), tasks[i].created_at))
+        }
+    }
+    // Best matches first; equal scores keep the chosen sort order.
+    if !view_options.filter.is_empty() {
+        indices.sort_by_key(|&i| std::cmp::Reverse(scores[i]));
+    }
+    if view_options.sink_completed {
+        indices.sort_by_key(|&i| tasks[i].completed_at.is_some());
+    }
+
+    // Subtasks follow their parent; ones whose parent is hidden stand alone.
+    let is_nested = |i: usize| {
+        tasks[i]
+            .parent_id
+            .is_some_and(|parent| indices.iter().any(|&j| tasks[j].id == parent))
+    };
+    let mut ordered = vec![];
+    for &i in indices.iter().filter(|&&i| !is_nested(i)) {
+        ordered.push(i);
+        ordered.extend(
+            indices
+                .iter()
+                .filter(|&&j| tasks[j].parent_id == Some(tasks[i].id)),
+        );
+    }
+    ordered
+}
+
+/// Puts `text` on the system clipboard, connecting to it on first use. The
+/// connection is kept open because on X11 the text is only available while it
+/// is.
+fn copy_to_clipboard(clipboard: &mut Option<arboard::Clipboard>, text: &str) -> Result<(), Error> {
+    let clipboard = match clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(arboard::Clipboard::new()?),
+    };
+    Ok(clipboard.set_text(text)?)
+}
+
+/// The next task after `from` whose name starts with `ch`, ignoring case and
+/// wrapping around to the top of the list.
+fn next_matching_index(tasks: &[Task], from: usize, ch: char) -> Option<usize> {
+    let ch = ch.to_lowercase().collect::<String>();
+    (1..=tasks.len())
+        .map(|step| (from + step) % tasks.len())
+        .find(|&index| tasks[index].name.to_lowercase().starts_with(&ch))
+}
+
+/// How many of the subtasks of `parent_id` are completed, out of all of them.
+fn child_progress(parent_id: usize, tasks: &[Task]) -> (usize, usize) {
+    tasks
+        .iter()
+        .filter(|task| task.parent_id == Some(parent_id))
+        .fold((0, 0), |(done, total), task| {
+            (done + task.completed_at.is_some() as usize, total + 1)
+        })
+}
+
+/// Splits `text` into spans around the characters matching `query`, the
+/// matched ones drawn with `highlight`. Case is ignored.
+fn highlight_match<'a>(text: &str, query: &str, style: Style, highlight: Style) -> Spans<'a> {
+    let matched: HashSet<usize> = SkimMatcherV2::default()
+        .ignore_case()
+        .fuzzy_indices(text, query)
+        .map(|(_, indices)| indices.into_iter().collect())
+        .unwrap_or_default();
+    let mut spans: Vec<Span> = vec![];
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_match = matched.contains(&i);
+        if is_match != run_matched && !run.is_empty() {
+            let run_style = if run_matched { highlight } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), run_style));
+        }
+        run_matched = is_match;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        let run_style = if run_matched { highlight } else { style };
+        spans.push(Span::styled(run, run_style));
+    }
+    Spans::from(spans)
+}
+
+/// Stale open tasks stand out: green for a day, yellow for a week, then red.
+fn age_color(created_at: DateTime<Utc>, now: DateTime<Utc>) -> Color {
+    let age = now - created_at;
+    if age <= chrono::Duration::days(1) {
+        Color::Green
+    } else if age <= chrono::Duration::days(7) {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+/// Times are stored in UTC but shown in the system's timezone.
+fn to_local_string(dt: &DateTime<Utc>) -> String {
+    dt.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn to_local_date(dt: &DateTime<Utc>) -> String {
+    dt.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
+/// Time left until `due`, or how long it is overdue, in its two largest units,
+/// e.g. `(2d 4h left)` or `(overdue 1d)`.
+fn format_countdown(due: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (due - now).num_seconds();
+    let minutes = seconds.abs() / 60;
+    let (days, hours, minutes) = (minutes / (60 * 24), minutes / 60 % 24, minutes % 60);
+    let amount = match (days, hours, minutes) {
+        (0, 0, minutes) => format!("{}m", minutes),
+        (0, hours, 0) => format!("{}h", hours),
+        (0, hours, minutes) => format!("{}h {}m", hours, minutes),
+        (days, 0, _) => format!("{}d", days),
+        (days, hours, _) => format!("{}d {}h", days, hours),
+    };
+    if seconds < 0 {
+        format!("(overdue {})", amount)
+    } else {
+        format!("({} left)", amount)
+    }
+}
+
+fn humanize(dt: &DateTime<Utc>) -> String {
+    let seconds = (Utc::now() - *dt).num_seconds();
+    let (amount, unit) = match seconds.abs() {
+        s if s < 60 => return String::from("just now"),
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 60 * 60 * 24 => (s / (60 * 60), "hour"),
+        s if s < 60 * 60 * 24 * 30 => (s / (60 * 60 * 24), "day"),
+        s if s < 60 * 60 * 24 * 365 => (s / (60 * 60 * 24 * 30), "month"),
+        s => (s / (60 * 60 * 24 * 365), "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    if seconds < 0 {
+        format!("in {} {}{}", amount, unit, plural)
+    } else {
+        format!("{} {}{} ago", amount, unit, plural)
+    }
+}
+
+fn render_popup<'a>(title: &'a str, size: Rect, theme: &Theme) -> (Block<'a>, Rect) {
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(Style::default().fg(theme.fg));
+    let area = centered_rect(60, 20, size);
+    (block, area)
+}
+
+/// Centres a bordered box just big enough for `lines` within `size`,
+/// wrapping them when the longest line does not fit across.
+fn fit_lines<'a>(lines: Vec<Spans>, size: Rect) -> (Rect, Vec<Spans<'a>>) {
+    let longest = lines.iter().map(Spans::width).max().unwrap_or(0) as u16;
+    let width = longest.saturating_add(2).min(size.width);
+    let lines: Vec<Spans> = lines
+        .iter()
+        .flat_map(|line| wrap_spans(&line.0, width.saturating_sub(2) as usize, 0))
+        .collect();
+    let height = (lines.len() as u16).saturating_add(2).min(size.height);
+    let area = Rect::new(
+        size.x + (size.width - width) / 2,
+        size.y + (size.height - height) / 2,
+        width,
+        height,
+    );
+    (area, lines)
+}
+
+/// Makes `area` at least `height` rows tall, still centred and within `size`.
+fn grow_rect(area: Rect, height: u16, size: Rect) -> Rect {
+    let height = height.max(area.height).min(size.height);
+    Rect {
+        y: size.y + (size.height - height) / 2,
+        height,
+        ..area
+    }
+}
+
+/// Popups never shrink below a usable size, though they are still clipped to
+/// `rect` on very small terminals.
+fn centered_rect(percent_x: u16, percent_y: u16, rect: Rect) -> Rect {
+    let width = ((rect.width as u32 * percent_x as u32 / 100) as u16)
+        .max(MIN_POPUP_WIDTH)
+        .min(rect.width);
+    let height = ((rect.height as u32 * percent_y as u32 / 100) as u16)
+        .max(MIN_POPUP_HEIGHT)
+        .min(rect.height);
+    Rect::new(
+        rect.x + (rect.width - width) / 2,
+        rect.y + (rect.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory for a test's task file, removed again when the
+    /// test ends, even if it fails.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("todo-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn db_path(&self) -> String {
+            let db_path = self.0.join("db.json");
+            db_path.to_str().expect("Temp path is UTF-8").to_string()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn legacy_ids_continue_past_the_highest_id() {
+        let mut db = Database::from_legacy(vec![Task::new(3, "third"), Task::new(1, "first")]);
+        assert_eq!(db.allocate_id(), 4);
+        db.tasks.clear();
+        assert_eq!(db.allocate_id(), 5);
+    }
+
+    #[test]
+    fn humanize_rounds_down_to_the_largest_unit() {
+        assert_eq!(humanize(&Utc::now()), "just now");
+        assert_eq!(
+            humanize(&(Utc::now() - chrono::Duration::hours(3))),
+            "3 hours ago"
+        );
+        assert_eq!(
+            humanize(&(Utc::now() - chrono::Duration::days(1))),
+            "1 day ago"
+        );
+        assert_eq!(
+            humanize(&(Utc::now() + chrono::Duration::hours(8 * 24 + 1))),
+            "in 8 days"
+        );
+    }
+
+    #[test]
+    fn moving_past_either_end_is_a_no_op() {
+        let mut tasks = vec![Task::new(0, "first"), Task::new(1, "second")];
+        let view_options = ViewOptions::default();
+        assert_eq!(move_task(&mut tasks, &view_options, 0, -1), None);
+        assert_eq!(move_task(&mut tasks, &view_options, 1, 1), None);
+        assert_eq!(move_task(&mut tasks, &view_options, 0, 1), Some(1));
+        assert_eq!(tasks[1].name, "first");
+    }
+
+    #[test]
+    fn moving_swaps_visible_neighbours() {
+        let mut tasks = vec![
+            Task::new(0, "first"),
+            Task {
+                completed_at: Some(Utc::now()),
+                ..Task::new(1, "done")
+            },
+            Task::new(2, "second"),
+        ];
+        let sunk = ViewOptions {
+            sink_completed: true,
+            ..ViewOptions::default()
+        };
+        assert_eq!(move_task(&mut tasks, &sunk, 0, 1), Some(2));
+        let names = |tasks: &[Task]| -> Vec<String> {
+            view_indices(tasks, &sunk)
+                .into_iter()
+                .map(|i| tasks[i].name.clone())
+                .collect()
+        };
+        assert_eq!(names(&tasks), ["second", "first", "done"]);
+        assert_eq!(move_task(&mut tasks, &sunk, 1, 1), None);
+        assert_eq!(names(&tasks), ["second", "first", "done"]);
+
+        let mut tasks = vec![
+            Task::new(0, "parent"),
+            Task::new(1, "other"),
+            Task {
+                parent_id: Some(0),
+                ..Task::new(2, "child")
+            },
+        ];
+        assert_eq!(names(&tasks), ["parent", "child", "other"]);
+        assert_eq!(move_task(&mut tasks, &sunk, 2, -1), Some(0));
+        assert_eq!(names(&tasks), ["other", "parent", "child"]);
+        assert_eq!(move_task(&mut tasks, &sunk, 2, -1), None);
+        assert_eq!(move_task(&mut tasks, &sunk, 1, 1), None);
+        assert_eq!(move_task(&mut tasks, &sunk, 0, 1), Some(1));
+        assert_eq!(names(&tasks), ["parent", "child", "other"]);
+    }
+
+    #[test]
+    fn selection_wraps_only_when_enabled() {
+        let mut task_list_state = ListState::default();
+        task_list_state.select(Some(0));
+        move_selection(&mut task_list_state, 3, -1, false);
+        assert_eq!(task_list_state.selected(), Some(0));
+        move_selection(&mut task_list_state, 3, -1, true);
+        assert_eq!(task_list_state.selected(), Some(2));
+        move_selection(&mut task_list_state, 3, 1, false);
+        assert_eq!(task_list_state.selected(), Some(2));
+        move_selection(&mut task_list_state, 3, 1, true);
+        assert_eq!(task_list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn jumping_in_an_empty_list_clears_the_selection() {
+        let mut task_list_state = ListState::default();
+        jump_selection(&mut task_list_state, 5, true);
+        assert_eq!(task_list_state.selected(), Some(4));
+        jump_selection(&mut task_list_state, 0, false);
+        assert_eq!(task_list_state.selected(), None);
+    }
+
+    #[test]
+    fn upcoming_tasks_include_overdue_and_soon_due_but_not_completed() {
+        let due_in = |name, hours| Task {
+            due_at: Some(Utc::now() + chrono::Duration::hours(hours)),
+            ..Task::new(0, name)
+        };
+        let tasks = vec![
+            due_in("later", 48),
+            due_in("soon", 2),
+            due_in("overdue", -5),
+            due_in("done", 1).toggle_completed(),
+            Task::new(0, "undated"),
+        ];
+        let names: Vec<&str> = upcoming_tasks(&tasks, chrono::Duration::hours(24))
+            .iter()
+            .map(|task| task.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["overdue", "soon"]);
+    }
+
+    #[test]
+    fn blank_task_names_are_rejected() {
+        let dir = TempDir::new("blank");
+        let db_path = &dir.db_path();
+        let mut db = Database::default();
+        assert!(matches!(
+            add_task_to_db(db_path, &mut db, "   ", None, None, None),
+            Err(Error::EmptyTaskName)
+        ));
+        assert!(db.tasks.is_empty());
+        assert!(!Path::new(db_path).exists());
+    }
+
+    #[test]
+    fn subtasks_are_listed_under_their_parent() {
+        let tasks = vec![
+            Task::new(0, "parent"),
+            Task::new(1, "other"),
+            Task {
+                parent_id: Some(0),
+                ..Task::new(2, "child")
+            },
+        ];
+        assert_eq!(view_indices(&tasks, &ViewOptions::default()), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn today_includes_overdue_but_not_tomorrow() {
+        use chrono::TimeZone;
+        let now = Local.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+        let due = |name, day, hour| Task {
+            due_at: Some(
+                Local
+                    .with_ymd_and_hms(2024, 3, day, hour, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Utc),
+            ),
+            ..Task::new(0, name)
+        };
+        assert!(is_due_today(&due("tonight", 10, 23), now));
+        assert!(is_due_today(&due("overdue", 8, 9), now));
+        assert!(!is_due_today(&due("overdue", 8, 9).toggle_completed(), now));
+        assert!(!is_due_today(&due("tomorrow", 11, 0), now));
+        assert!(!is_due_today(&Task::new(0, "undated"), now));
+    }
+
+    #[test]
+    fn streak_counts_consecutive_completion_days() {
+        use chrono::TimeZone;
+        let done = |day| Task {
+            completed_at: Some(
+                Local
+                    .with_ymd_and_hms(2024, 3, day, 12, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Utc),
+            ),
+            ..Task::new(0, "done")
+        };
+        let tasks = vec![done(10), done(9), done(9), done(8), done(6)];
+        let day = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        assert_eq!(current_streak(&tasks, day(10)), 3);
+        assert_eq!(current_streak(&tasks, day(11)), 3);
+        assert_eq!(current_streak(&tasks, day(12)), 0);
+        assert_eq!(current_streak(&tasks, day(7)), 1);
+        assert_eq!(current_streak(&[], day(10)), 0);
+    }
+
+    #[test]
+    fn search_matches_fuzzily_and_ranks_by_score() {
+        let tasks = vec![
+            Task::new(1, "book a meeting room"),
+            Task::new(2, "buy milk"),
+            Task::new(3, "water plants"),
+            Task::new(4, "BMK report"),
+        ];
+        let mut view = ViewOptions::default();
+        assert_eq!(view_indices(&tasks, &view), vec![0, 1, 2, 3]);
+        view.filter = String::from("bmk");
+        assert_eq!(view_indices(&tasks, &view), vec![3, 1]);
+        view.filter = String::from("t5");
+        assert_eq!(view_indices(&[Task::new(0, "Task 5")], &view), vec![0]);
+    }
+
+    #[test]
+    fn older_databases_are_migrated_to_the_current_version() {
+        let legacy =
+            r#"[{"id":3,"name":"old","created_at":"2023-09-14T16:21:21Z","completed_at":null}]"#;
+        let (db, version) = parse_db(legacy, DbFormat::Json).unwrap();
+        assert_eq!((version, db.version, db.next_id), (0, DB_VERSION, 4));
+        assert_eq!(db.tasks[0].name, "old");
+
+        let (db, version) = parse_db(r#"{"next_id":7,"tasks":[]}"#, DbFormat::Json).unwrap();
+        assert_eq!((version, db.version, db.next_id), (1, DB_VERSION, 7));
+
+        let without_uuids = format!(
+            r#"{{"version":2,"next_id":2,"tasks":[{task},{task}]}}"#,
+            task =
+                r#"{"id":0,"name":"old","created_at":"2023-09-14T16:21:21Z","completed_at":null}"#
+        );
+        let (db, version) = parse_db(&without_uuids, DbFormat::Json).unwrap();
+        assert_eq!(version, 2);
+        assert!(!db.tasks[0].uuid.is_nil());
+        assert_ne!(db.tasks[0].uuid, db.tasks[1].uuid);
+
+        let current = serde_json::to_string(&Database::default()).unwrap();
+        assert_eq!(parse_db(&current, DbFormat::Json).unwrap().1, DB_VERSION);
+        assert!(parse_db(r#"{"version":99,"next_id":0,"tasks":[]}"#, DbFormat::Json).is_err());
+    }
+
+    #[test]
+    fn rebinding_a_key_takes_it_from_its_old_action() {
+        let keys = HashMap::from([
+            (Action::DeleteTask, String::from("x")),
+            (Action::Quit, String::from("d")),
+        ]);
+        let key_bindings = KeyBindings::from_config(&keys).unwrap();
+        assert_eq!(
+            key_bindings.action(KeyCode::Char('x')),
+            Some(Action::DeleteTask)
+        );
+        assert_eq!(key_bindings.action(KeyCode::Char('d')), Some(Action::Quit));
+        assert_eq!(key_bindings.action(KeyCode::Char('q')), None);
+        assert_eq!(
+            key_bindings.action(KeyCode::Home),
+            Some(Action::SelectFirst)
+        );
+
+        let keys = HashMap::from([(Action::Quit, String::from("Ctrl"))]);
+        assert!(KeyBindings::from_config(&keys).is_err());
+    }
+
+    #[test]
+    fn clearing_completed_tasks_moves_them_to_the_archive() {
+        let dir = TempDir::new("clear");
+        let db_path = &dir.db_path();
+        let mut db = Database::from_legacy(vec![
+            Task::new(0, "open"),
+            Task::new(1, "done").toggle_completed(),
+            Task::new(2, "also done").toggle_completed(),
+        ]);
+        let mut archive = vec![];
+        assert_eq!(clear_completed(db_path, &mut db, &mut archive).unwrap(), 2);
+        assert_eq!(db.tasks.len(), 1);
+        assert_eq!(read_archive(db_path).unwrap().len(), 2);
+        assert_eq!(clear_completed(db_path, &mut db, &mut archive).unwrap(), 0);
+    }
+
+    #[test]
+    fn clearing_keeps_subtasks_with_their_parent() {
+        let dir = TempDir::new("clear-subtasks");
+        let db_path = &dir.db_path();
+        let subtask = |id, parent_id, name| Task {
+            parent_id: Some(parent_id),
+            ..Task::new(id, name)
+        };
+        let mut db = Database::from_legacy(vec![
+            Task::new(0, "done parent").toggle_completed(),
+            subtask(1, 0, "open child"),
+            Task::new(2, "open parent"),
+            subtask(3, 2, "done child").toggle_completed(),
+            Task::new(4, "all done").toggle_completed(),
+            subtask(5, 4, "done too").toggle_completed(),
+        ]);
+        let mut archive = vec![];
+        assert_eq!(clear_completed(db_path, &mut db, &mut archive).unwrap(), 2);
+        let names: Vec<&str> = archive.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["all done", "done too"]);
+        assert_eq!(db.tasks.len(), 4);
+    }
+
+    #[test]
+    fn pages_hold_as_many_whole_tasks_as_fit() {
+        assert_eq!(page_size(Rect::new(0, 0, 20, 12), false), 10);
+        assert_eq!(page_size(Rect::new(0, 0, 20, 12), true), 11);
+        assert_eq!(page_size(Rect::new(0, 0, 20, 1), false), 1);
+        let pages = ListPages::new(vec![1, 3, 1, 4, 2, 2], 5);
+        assert_eq!(pages.starts, vec![0, 3, 4]);
+        assert_eq!(pages.page_of(2), 0);
+        assert_eq!(pages.start_of(3), 3);
+        assert_eq!(pages.page_of(5), 2);
+        assert_eq!(pages.task_at_row(0, 0), Some(0));
+        assert_eq!(pages.task_at_row(0, 3), Some(1));
+        assert_eq!(pages.task_at_row(1, 4), Some(2));
+        assert_eq!(pages.task_at_row(5, 3), Some(5));
+        assert_eq!(pages.task_at_row(5, 4), None);
+    }
+
+    #[test]
+    fn long_names_wrap_at_word_boundaries() {
+        let text = |lines: Vec<Spans>| -> Vec<String> {
+            lines
+                .iter()
+                .map(|line| line.0.iter().map(|span| span.content.as_ref()).collect())
+                .collect()
+        };
+        let spans = [Span::raw("[ ] "), Span::raw("buy milk and eggs")];
+        assert_eq!(
+            text(wrap_spans(&spans, 12, 4)),
+            vec!["[ ] buy milk", "    and eggs"]
+        );
+        assert_eq!(
+            text(wrap_spans(&spans, 40, 4)),
+            vec!["[ ] buy milk and eggs"]
+        );
+        let spans = [Span::raw("supercalifragilistic")];
+        assert_eq!(
+            text(wrap_spans(&spans, 8, 0)),
+            vec!["supercal", "ifragili", "stic"]
+        );
+        assert_eq!(text(wrap_spans(&[], 8, 0)), vec![""]);
+    }
+
+    #[test]
+    fn wide_characters_wrap_by_display_width() {
+        let text = |lines: Vec<Spans>| -> Vec<String> {
+            lines
+                .iter()
+                .map(|line| line.0.iter().map(|span| span.content.as_ref()).collect())
+                .collect()
+        };
+        let spans = [Span::raw("日本語日本語日本語")];
+        assert_eq!(
+            text(wrap_spans(&spans, 6, 0)),
+            vec!["日本語", "日本語", "日本語"]
+        );
+        assert_eq!(
+            text(wrap_spans(&[Span::raw("買う 牛乳 and eggs")], 8, 2)),
+            vec!["買う", "  牛乳", "  and", "  eggs"]
+        );
+        assert_eq!(wrap_spans(&spans, 1, 0).len(), 9);
+    }
+
+    #[test]
+    fn databases_round_trip_through_every_format() {
+        let mut task = Task::new(0, "buy milk");
+        task.tags = vec![String::from("shopping")];
+        let mut db = Database::from_legacy(vec![task, Task::new(1, "done").toggle_completed()]);
+        for format in [DbFormat::Json, DbFormat::Toml, DbFormat::Yaml] {
+            db.format = format;
+            let content = String::from_utf8(format.serialize(&db).unwrap()).unwrap();
+            let (parsed, version) = parse_db(&content, format).unwrap();
+            assert_eq!(version, DB_VERSION);
+            assert_eq!(parsed.next_id, 2);
+            assert_eq!(parsed.tasks[0].tags, vec!["shopping"]);
+            assert_eq!(parsed.tasks[1].completed_at, db.tasks[1].completed_at);
+        }
+        assert_eq!(DbFormat::from_path("tasks.yml"), DbFormat::Yaml);
+        assert_eq!(DbFormat::from_path("./data/db.json"), DbFormat::Json);
+        assert_eq!(DbFormat::from_path("tasks"), DbFormat::Json);
+    }
+
+    #[test]
+    fn writes_keep_only_the_most_recent_backups() {
+        let dir = TempDir::new("backups");
+        let db_path = &dir.db_path();
+        let mut db = Database::default();
+        write_db(db_path, &db).unwrap();
+        assert!(list_backups(db_path).unwrap().is_empty());
+        for name in ["a", "b", "c"] {
+            add_task_to_db(db_path, &mut db, name, None, None, None).unwrap();
+            thread::sleep(Duration::from_millis(2));
+        }
+        assert_eq!(list_backups(db_path).unwrap().len(), 3);
+
+        rotate_backups(db_path, 1).unwrap();
+        let backups = list_backups(db_path).unwrap();
+        assert_eq!(backups.len(), 1);
+        let (newest, _) =
+            parse_db(&fs::read_to_string(&backups[0]).unwrap(), DbFormat::Json).unwrap();
+        assert_eq!(newest.tasks.len(), 2);
+    }
+
+    #[test]
+    fn debounced_edits_reach_the_disk_on_flush() {
+        let dir = TempDir::new("debounce");
+        let db_path = &dir.db_path();
+        let mut db = Database::default();
+        write_db(db_path, &db).unwrap();
+        db.debounce = true;
+        add_task_to_db(db_path, &mut db, "a", None, None, None).unwrap();
+        add_task_to_db(db_path, &mut db, "b", None, None, None).unwrap();
+        assert!(db.modified_at.is_some());
+        assert!(read_db(db_path, DbFormat::Json).unwrap().tasks.is_empty());
+
+        flush_db(db_path, &mut db).unwrap();
+        assert!(db.modified_at.is_none());
+        assert_eq!(read_db(db_path, DbFormat::Json).unwrap().tasks.len(), 2);
+        assert_eq!(list_backups(db_path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn our_own_writes_are_not_changes_on_disk() {
+        let dir = TempDir::new("watch");
+        let db_path = &dir.db_path();
+        let mut db = Database::default();
+        write_db(db_path, &db).unwrap();
+        assert!(!changed_on_disk(db_path, &db));
+        db.debounce = true;
+        add_task_to_db(
+            db_path,
+            &mut db,
+            "edited before the event",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(!changed_on_disk(db_path, &db));
+
+        let mut elsewhere = read_db(db_path, DbFormat::Json).unwrap();
+        add_task_to_db(db_path, &mut elsewhere, "from outside", None, None, None).unwrap();
+        assert!(changed_on_disk(db_path, &db));
+    }
+
+    #[test]
+    fn undoing_a_delete_restores_the_task_with_its_subtasks() {
+        let dir = TempDir::new("undo");
+        let db_path = &dir.db_path();
+        let mut db = Database::from_legacy(vec![
+            Task::new(0, "parent"),
+            Task::new(1, "other"),
+            Task {
+                parent_id: Some(0),
+                ..Task::new(2, "child")
+            },
+        ]);
+        let mut task_list_state = ListState::default();
+        task_list_state.select(Some(0));
+        let removed = remove_task_at_index(
+            db_path,
+            &mut db,
+            &mut task_list_state,
+            &ViewOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(db.tasks.len(), 1);
+
+        restore_tasks(db_path, &mut db, removed).unwrap();
+        let names: Vec<&str> = db.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["parent", "other", "child"]);
+    }
+
+    #[test]
+    fn reloading_picks_up_changes_made_on_disk() {
+        let dir = TempDir::new("reload");
+        let db_path = &dir.db_path();
+        let mut db = Database::default();
+        write_db(db_path, &db).unwrap();
+        let mut elsewhere = read_db(db_path, DbFormat::Json).unwrap();
+        add_task_to_db(db_path, &mut elsewhere, "from outside", None, None, None).unwrap();
+
+        db.debounce = true;
+        reload_db(db_path, &mut db).unwrap();
+        assert_eq!(db.tasks.len(), 1);
+        assert!(db.debounce);
+        add_task_to_db(db_path, &mut db, "not saved yet", None, None, None).unwrap();
+        assert!(matches!(
+            reload_db(db_path, &mut db),
+            Err(Error::UnsavedChanges)
+        ));
+        assert_eq!(db.tasks.len(), 2);
+    }
+
+    #[test]
+    fn minutes_are_shown_in_hours_and_minutes() {
+        assert_eq!(fmt_minutes(0), "0m");
+        assert_eq!(fmt_minutes(45), "45m");
+        assert_eq!(fmt_minutes(120), "2h");
+        assert_eq!(fmt_minutes(135), "2h15m");
+        assert_eq!(parse_estimate(" 30 "), Ok(Some(30)));
+        assert_eq!(parse_estimate(""), Ok(None));
+        assert!(parse_estimate("half an hour").is_err());
+    }
+
+    #[test]
+    fn search_highlights_the_matched_characters() {
+        let highlight = Style::default().add_modifier(Modifier::BOLD);
+        let spans = highlight_match("Buy milk", "MILK", Style::default(), highlight);
+        let parts: Vec<(&str, bool)> = spans
+            .0
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style == highlight))
+            .collect();
+        assert_eq!(parts, vec![("Buy ", false), ("milk", true)]);
+
+        let mut task = Task::new(0, "call the bank");
+        task.notes = String::from("Ask about the MORTGAGE rate");
+        let view = ViewOptions {
+            filter: String::from("mortgage"),
+            ..ViewOptions::default()
+        };
+        assert_eq!(view_indices(&[Task::new(1, "other"), task], &view), vec![1]);
+    }
+
+    #[test]
+    fn overdue_tasks_are_reported_once() {
+        let now = Utc::now();
+        let due = |id, hours| Task {
+            due_at: Some(now + chrono::Duration::hours(hours)),
+            ..Task::new(id, "due")
+        };
+        let mut tasks = vec![due(0, -1), due(1, 1)];
+        let mut notified = HashSet::new();
+        let ids = |tasks: Vec<&Task>| tasks.iter().map(|task| task.id).collect::<Vec<_>>();
+        assert_eq!(ids(newly_overdue(&tasks, &mut notified, now)), vec![0]);
+        assert!(newly_overdue(&tasks, &mut notified, now).is_empty());
+        tasks.push(due(2, -2).toggle_completed());
+        let later = now + chrono::Duration::hours(2);
+        assert_eq!(ids(newly_overdue(&tasks, &mut notified, later)), vec![1]);
+    }
+
+    #[test]
+    fn only_completions_on_the_given_day_count_toward_the_goal() {
+        use chrono::TimeZone;
+        let done = |day, hour| Task {
+            completed_at: Some(
+                Local
+                    .with_ymd_and_hms(2024, 3, day, hour, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Utc),
+            ),
+            ..Task::new(0, "done")
+        };
+        let tasks = vec![done(10, 0), done(10, 23), done(9, 23), Task::new(1, "open")];
+        let day = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        assert_eq!(completed_today(&tasks, day(10)), 2);
+        assert_eq!(completed_today(&tasks, day(9)), 1);
+        assert_eq!(completed_today(&tasks, day(11)), 0);
+    }
+
+    #[test]
+    fn parents_count_their_completed_subtasks() {
+        let subtask = |id, parent_id| Task {
+            parent_id: Some(parent_id),
+            ..Task::new(id, "subtask")
+        };
+        let tasks = vec![
+            Task::new(0, "groceries").toggle_completed(),
+            subtask(1, 0).toggle_completed(),
+            subtask(2, 0),
+            subtask(3, 0).toggle_completed(),
+            Task::new(4, "laundry"),
+        ];
+        assert_eq!(child_progress(0, &tasks), (2, 3));
+        assert_eq!(child_progress(4, &tasks), (0, 0));
+        assert_eq!(child_progress(1, &tasks), (0, 0));
+    }
+
+    #[test]
+    fn text_imports_add_one_task_per_non_blank_line() {
+        let mut db = Database::from_legacy(vec![Task::new(0, "existing")]);
+        assert_eq!(add_lines(&mut db, "  buy milk \n\n\t\ncall mum\r\n"), 2);
+        let names: Vec<&str> = db.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["existing", "buy milk", "call mum"]);
+        assert_eq!(db.tasks[1].id, 1);
+        assert_eq!(db.tasks[2].id, 2);
+    }
+
+    #[test]
+    fn due_dates_accept_relative_shorthands() {
+        use chrono::TimeZone;
+        // A Wednesday.
+        let now = Local.with_ymd_and_hms(2024, 5, 15, 9, 0, 0).unwrap();
+        let due = |input| parse_due(input, now).map(|due_at| to_local_date(&due_at));
+        assert_eq!(due("2024-06-01").as_deref(), Some("2024-06-01"));
+        assert_eq!(due("today").as_deref(), Some("2024-05-15"));
+        assert_eq!(due("Tomorrow").as_deref(), Some("2024-05-16"));
+        assert_eq!(due("+3d").as_deref(), Some("2024-05-18"));
+        assert_eq!(due("+2w").as_deref(), Some("2024-05-29"));
+        assert_eq!(due("next monday").as_deref(), Some("2024-05-20"));
+        assert_eq!(due("fri").as_deref(), Some("2024-05-17"));
+        assert_eq!(due("wednesday").as_deref(), Some("2024-05-22"));
+        assert_eq!(due("someday"), None);
+        assert_eq!(due("+d"), None);
+        assert_eq!(due("+3y"), None);
+    }
+
+    #[test]
+    fn countdowns_show_the_two_largest_units() {
+        let now = Utc::now();
+        let due = |minutes| now + chrono::Duration::minutes(minutes);
+        assert_eq!(
+            format_countdown(due(2 * 24 * 60 + 4 * 60 + 5), now),
+            "(2d 4h left)"
+        );
+        assert_eq!(format_countdown(due(3 * 24 * 60), now), "(3d left)");
+        assert_eq!(format_countdown(due(3 * 60 + 20), now), "(3h 20m left)");
+        assert_eq!(format_countdown(due(60), now), "(1h left)");
+        assert_eq!(format_countdown(due(15), now), "(15m left)");
+        assert_eq!(format_countdown(due(-24 * 60 - 30), now), "(overdue 1d)");
+        assert_eq!(format_countdown(due(-90), now), "(overdue 1h 30m)");
+    }
+
+    #[test]
+    fn letters_jump_to_the_next_matching_task() {
+        let tasks: Vec<Task> = ["apples", "Bread", "avocado", "beans"]
+            .iter()
+            .enumerate()
+            .map(|(id, name)| Task::new(id, name))
+            .collect();
+        assert_eq!(next_matching_index(&tasks, 0, 'a'), Some(2));
+        assert_eq!(next_matching_index(&tasks, 2, 'a'), Some(0));
+        assert_eq!(next_matching_index(&tasks, 0, 'b'), Some(1));
+        assert_eq!(next_matching_index(&tasks, 1, 'B'), Some(3));
+        assert_eq!(next_matching_index(&tasks, 0, 'z'), None);
+        assert_eq!(next_matching_index(&[], 0, 'a'), None);
+    }
+
+    #[test]
+    fn priority_sort_puts_high_first_then_oldest() {
+        let task = |id, priority, days_ago| Task {
+            priority,
+            created_at: Utc::now() - chrono::Duration::days(days_ago),
+            ..Task::new(id, "task")
+        };
+        let tasks = vec![
+            task(0, Priority::Low, 3),
+            task(1, Priority::High, 1),
+            task(2, Priority::Medium, 2),
+            task(3, Priority::High, 2),
+        ];
+        let view_options = ViewOptions {
+            sort_mode: SortMode::Priority,
+            ..ViewOptions::default()
+        };
+        assert_eq!(view_indices(&tasks, &view_options), vec![3, 1, 2, 0]);
+        assert_eq!(tasks[0].id, 0);
+    }
+
+    #[test]
+    fn date_ranges_may_be_open_at_either_end() {
+        let range = parse_date_range("2024-01-01..2024-01-07").unwrap().unwrap();
+        assert_eq!(range.label(), "2024-01-01..2024-01-07");
+        let start = range.from.unwrap();
+        assert!(range.contains(start));
+        assert!(range.contains(start + chrono::Duration::days(7) - chrono::Duration::seconds(1)));
+        assert!(
+            range.contains(start + chrono::Duration::days(7) - chrono::Duration::milliseconds(1))
+        );
+        assert!(!range.contains(start + chrono::Duration::days(7)));
+        assert!(!range.contains(start - chrono::Duration::seconds(1)));
+
+        let open = parse_date_range("2024-01-01..").unwrap().unwrap();
+        assert!(open.to.is_none() && open.contains(start + chrono::Duration::days(365)));
+        assert_eq!(
+            parse_date_range("..2024-01-01").unwrap().unwrap().from,
+            None
+        );
+        let one_day = parse_date_range("2024-01-01..2024-01-01").unwrap().unwrap();
+        assert!(one_day.contains(start) && one_day.from < one_day.to);
+        assert_eq!(parse_date_range("  "), Ok(None));
+        assert!(parse_date_range("2024-01-07..2024-01-01").is_err());
+        assert!(parse_date_range("last week").is_err());
+    }
+
+    #[test]
+    fn snoozing_pushes_the_due_date_or_starts_from_now() {
+        let now = Utc::now();
+        let day = chrono::Duration::days(1);
+        let undated = Task::new(0, "undated").snooze(day, now);
+        assert_eq!(undated.due_at, Some(now + day));
+        let dated = Task {
+            due_at: Some(now - day),
+            ..Task::new(1, "overdue")
+        }
+        .snooze(day * 3, now);
+        assert_eq!(dated.due_at, Some(now + day * 2));
+    }
+
+    #[test]
+    fn tags_are_counted_most_used_first() {
+        let tasks = vec![
+            Task {
+                tags: vec![String::from("home"), String::from("work")],
+                ..Task::new(0, "first")
+            },
+            Task {
+                tags: vec![String::from("work")],
+                ..Task::new(1, "second")
+            },
+            Task {
+                tags: vec![String::from("errands")],
+                ..Task::new(2, "third")
+            },
+            Task::new(3, "untagged"),
+        ];
+        assert_eq!(
+            tag_counts(&tasks),
+            vec![
+                (String::from("work"), 2),
+                (String::from("errands"), 1),
+                (String::from("home"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn command_line_ids_must_name_an_existing_task() {
+        let tasks = vec![Task::new(0, "first"), Task::new(4, "second")];
+        assert_eq!(find_task(&tasks, "4").unwrap().name, "second");
+        assert!(matches!(
+            find_task(&tasks, "1"),
+            Err(Error::TaskNotFound(id)) if id == "1"
+        ));
+        assert!(find_task(&tasks, "second").is_err());
+    }
+
+    #[test]
+    fn reminders_fire_once_their_lead_begins() {
+        let now = Utc::now();
+        let tasks = vec![
+            Task {
+                due_at: Some(now + chrono::Duration::hours(2)),
+                reminder_lead: Some(chrono::Duration::hours(3)),
+                ..Task::new(0, "soon")
+            },
+            Task {
+                due_at: Some(now + chrono::Duration::hours(5)),
+                reminder_lead: Some(chrono::Duration::hours(3)),
+                ..Task::new(1, "later")
+            },
+            Task {
+                due_at: Some(now + chrono::Duration::hours(1)),
+                ..Task::new(2, "no reminder")
+            },
+        ];
+        let mut reminded = HashSet::new();
+        let names = |due: Vec<&Task>| -> Vec<String> {
+            due.into_iter().map(|task| task.name.clone()).collect()
+        };
+        assert_eq!(
+            names(due_reminders(&tasks, &mut reminded, now)),
+            vec!["soon"]
+        );
+        assert!(due_reminders(&tasks, &mut reminded, now).is_empty());
+        assert_eq!(
+            names(due_reminders(
+                &tasks,
+                &mut reminded,
+                now + chrono::Duration::hours(2)
+            )),
+            vec!["later"]
+        );
+
+        let json = serde_json::to_string(&tasks[0]).unwrap();
+        assert!(json.contains("\"reminder_lead\":10800"));
+        let parsed: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.reminder_lead, Some(chrono::Duration::hours(3)));
+    }
+
+    #[test]
+    fn deferred_tasks_stay_hidden_until_they_start() {
+        let now = Utc::now();
+        let tasks = vec![
+            Task {
+                start_at: Some(now + chrono::Duration::days(2)),
+                ..Task::new(0, "next week")
+            },
+            Task {
+                start_at: Some(now - chrono::Duration::hours(1)),
+                ..Task::new(1, "started")
+            },
+            Task::new(2, "whenever"),
+        ];
+        assert!(is_deferred(&tasks[0], now));
+        assert!(!is_deferred(&tasks[0], now + chrono::Duration::days(3)));
+        assert!(!is_deferred(&tasks[1], now));
+        assert_eq!(view_indices(&tasks, &ViewOptions::default()), vec![1, 2]);
+        let revealed = ViewOptions {
+            show_deferred: true,
+            ..ViewOptions::default()
+        };
+        assert_eq!(view_indices(&tasks, &revealed), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn weekly_summary_counts_the_last_seven_days() {
+        let now = Utc::now();
+        let days_ago = |days: i64| now - chrono::Duration::days(days);
+        let tasks = vec![
+            Task {
+                created_at: days_ago(2),
+                completed_at: Some(days_ago(1)),
+                ..Task::new(0, "done this week")
+            },
+            Task {
+                created_at: days_ago(10),
+                completed_at: Some(days_ago(8)),
+                ..Task::new(1, "done last week")
+            },
+            Task {
+                created_at: days_ago(10),
+                due_at: Some(days_ago(9)),
+                ..Task::new(2, "long overdue")
+            },
+            Task {
+                created_at: days_ago(3),
+                due_at: Some(now + chrono::Duration::days(1)),
+                ..Task::new(3, "due tomorrow")
+            },
+        ];
+        assert_eq!(
+            weekly_summary(&tasks, now),
+            Summary {
+                created: 2,
+                completed: 1,
+                overdue: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn dragged_tasks_land_next_to_the_drop_target() {
+        let names = |tasks: &[Task]| -> Vec<String> {
+            tasks.iter().map(|task| task.name.clone()).collect()
+        };
+        let mut tasks: Vec<Task> = ["a", "b", "c", "d"]
+            .iter()
+            .enumerate()
+            .map(|(id, name)| Task::new(id, name))
+            .collect();
+        move_task_to(&mut tasks, 0, 2);
+        assert_eq!(names(&tasks), vec!["b", "c", "a", "d"]);
+        move_task_to(&mut tasks, 3, 0);
+        assert_eq!(names(&tasks), vec!["d", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn completed_tasks_sink_below_open_ones_after_sorting() {
+        let now = Utc::now();
+        let tasks = vec![
+            Task {
+                completed_at: Some(now),
+                priority: Priority::High,
+                ..Task::new(0, "done")
+            },
+            Task {
+                priority: Priority::Low,
+                ..Task::new(1, "low")
+            },
+            Task {
+                priority: Priority::High,
+                ..Task::new(2, "high")
+            },
+        ];
+        let sunk = ViewOptions {
+            sink_completed: true,
+            ..ViewOptions::default()
+        };
+        assert_eq!(view_indices(&tasks, &sunk), vec![1, 2, 0]);
+        let by_priority = ViewOptions {
+            sort_mode: SortMode::Priority,
+            ..sunk
+        };
+        assert_eq!(view_indices(&tasks, &by_priority), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn every_key_can_be_scrolled_into_view_on_a_small_terminal() {
+        use tui::backend::TestBackend;
+        let key_bindings = KeyBindings::default();
+        let size = Rect::new(0, 0, 80, 24);
+        let lines = help_lines(&key_bindings, &Theme::dark());
+        let scroll = help_max_scroll(lines.len(), size);
+        assert!(scroll > 0);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal
+            .draw(|rect| rect.render_widget(render_help(lines, scroll, &Theme::dark()), size))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..size.height)
+            .map(|y| {
+                (0..size.width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect();
+        let last = key_bindings.bindings.last().unwrap().0.description();
+        assert!(rows.iter().any(|row| row.contains(last)));
+        assert!(rows[22].contains("Alt+letter"));
+    }
+
+    #[test]
+    fn popups_grow_to_fit_their_contents() {
+        let size = Rect::new(0, 0, 80, 24);
+        let area = centered_rect(60, 20, size);
+        assert_eq!(grow_rect(area, 3, size), area);
+        let grown = grow_rect(area, 7, size);
+        assert_eq!((grown.height, grown.y, grown.x), (7, 8, area.x));
+        assert_eq!(grow_rect(area, 40, size).height, 24);
+    }
+
+    #[test]
+    fn the_whole_tutorial_fits_on_a_small_terminal() {
+        let lines = tutorial_lines(&KeyBindings::default(), &Theme::dark());
+        let count = lines.len() as u16;
+        let size = Rect::new(0, 0, 80, 24);
+        let (area, wrapped) = fit_lines(lines.clone(), size);
+        assert_eq!(area.height, count + 2);
+        assert!(area.width <= size.width);
+        assert!(wrapped
+            .iter()
+            .all(|line| line.width() + 2 <= area.width as usize));
+
+        let (narrow, wrapped) = fit_lines(lines, Rect::new(0, 0, 30, 24));
+        assert_eq!(narrow.width, 30);
+        assert!(wrapped.len() > count as usize);
+        assert_eq!(narrow.height, wrapped.len() as u16 + 2);
+    }
+
+    #[test]
+    fn enter_only_completes_tasks_on_the_tasks_tab() {
+        let action = KeyBindings::default().action(KeyCode::Enter).unwrap();
+        assert_eq!(action, Action::Complete);
+        assert!(!action.applies_in(MenuItem::Home));
+        assert!(!action.applies_in(MenuItem::Archive));
+        assert!(action.applies_in(MenuItem::Tasks));
+        assert!(Action::AddTask.applies_in(MenuItem::Home));
+    }
+
+    #[test]
+    fn popups_keep_a_minimum_size_within_the_terminal() {
+        let popup = centered_rect(60, 20, Rect::new(0, 0, 100, 10));
+        assert_eq!(popup, Rect::new(20, 2, 60, 5));
+        let tiny = Rect::new(0, 0, 12, 3);
+        assert_eq!(centered_rect(60, 20, tiny), tiny);
+    }
+
+    #[test]
+    fn merging_skips_duplicates_and_assigns_fresh_ids() {
+        let existing = Task::new(0, "shared");
+        let mut db = Database::from_legacy(vec![existing.clone()]);
+        let imported = merge_tasks(&mut db, vec![existing.clone(), Task::new(0, "new")], false);
+        assert_eq!(imported, 1);
+        assert_eq!(db.tasks.len(), 2);
+        assert_eq!(db.tasks[1].name, "new");
+        assert_eq!(db.tasks[1].id, 1);
+
+        let renamed = Task {
+            name: String::from("renamed elsewhere"),
+            ..existing.clone()
+        };
+        assert_eq!(merge_tasks(&mut db, vec![renamed.clone()], false), 0);
+        let legacy_copy = Task {
+            uuid: Uuid::new_v4(),
+            ..existing
+        };
+        assert_eq!(merge_tasks(&mut db, vec![legacy_copy], true), 0);
+        assert_eq!(merge_tasks(&mut db, vec![renamed], true), 1);
+    }
+
+    #[test]
+    fn merged_subtasks_attach_to_a_parent_that_already_exists() {
+        let parent = Task::new(5, "parent");
+        let mut db = Database::from_legacy(vec![Task::new(0, "first"), parent.clone()]);
+        let child = Task {
+            parent_id: Some(8),
+            ..Task::new(9, "child")
+        };
+        let incoming = vec![Task { id: 8, ..parent }, child];
+        assert_eq!(merge_tasks(&mut db, incoming, false), 1);
+        assert_eq!(db.tasks[2].name, "child");
+        assert_eq!(db.tasks[2].parent_id, Some(5));
+    }
+
+    #[test]
+    fn removing_first_task_keeps_selection_at_top() {
+        let mut tasks = vec![Task::new(0, "first"), Task::new(1, "second")];
+        let mut task_list_state = ListState::default();
+        task_list_state.select(Some(0));
+
+        assert!(
+            remove_selected_task(&mut tasks, &mut task_list_state, &ViewOptions::default())
+                .is_some()
+        );
+        assert_eq!(task_list_state.selected(), Some(0));
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].name, "second");
+    }
+
+    #[test]
+    fn toggling_a_recurring_task_queues_one_next_occurrence() {
+        let mut db = Database::from_legacy(vec![Task {
+            recurrence: Some(Recurrence::Daily),
+            ..Task::new(0, "water plants")
+        }]);
+        for _ in 0..3 {
+            toggle_task_at(&mut db, 0);
+        }
+        assert!(db.tasks[0].completed_at.is_some());
+        let successors: Vec<&Task> = db
+            .tasks
+            .iter()
+            .filter(|task| task.spawned_from == Some(db.tasks[0].uuid))
+            .collect();
+        assert_eq!(db.tasks.len(), 2);
+        assert_eq!(successors.len(), 1);
+        assert!(successors[0].completed_at.is_none());
+    }
+
+    #[test]
+    fn monthly_recurrence_clamps_to_month_end() {
+        let jan_31 = "2024-01-31T09:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let next = Recurrence::Monthly.advance(jan_31);
+        assert_eq!(
+            next,
+            "2024-02-29T09:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn removing_from_empty_list_is_a_no_op() {
+        let mut tasks = vec![];
+        let mut task_list_state = ListState::default();
+        task_list_state.select(Some(0));
+
+        assert!(
+            remove_selected_task(&mut tasks, &mut task_list_state, &ViewOptions::default())
+                .is_none()
+        );
+        assert_eq!(task_list_state.selected(), Some(0));
+    }
 }